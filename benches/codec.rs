@@ -0,0 +1,115 @@
+//! Throughput of the binary format and share-string codecs.
+//!
+//! Run with `cargo bench --no-default-features`, since the benchmark binary cannot
+//! link against the Node-API symbols pulled in by the default `napi` feature.
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use psi_spell_encode::{decode_spell, encode_spell, Side, Spell};
+
+const TRICKS: [&str; 6] = [
+    "psi:trick_add_motion",
+    "psi:operator_vector_sum",
+    "psi:selector_caster",
+    "psi:operator_entity_look",
+    "psi:constant_number",
+    "psi:trick_blink",
+];
+
+/// A spell with `len` pieces laid out row by row over the 9x9 grid, mixing constants,
+/// wired params and comments.
+fn sample(len: usize) -> Spell {
+    let mut builder = Spell::builder()
+        .name(format!("Bench {len}"))
+        .add_mod("psi", "2.0.0");
+    for i in 0..len {
+        let (x, y) = ((i % 9) as u8, (i / 9) as u8);
+        let piece = builder.add_piece(x, y, TRICKS[i % TRICKS.len()]);
+        builder = match i % 3 {
+            0 => piece.constant(format!("{i}")).done(),
+            1 => piece
+                .param("_target", Side::Left)
+                .param("_vector", Side::Top)
+                .comment("push")
+                .done(),
+            _ => piece.param("_number", Side::Bottom).done(),
+        };
+    }
+    builder.build()
+}
+
+fn samples() -> [(&'static str, Spell); 3] {
+    [
+        ("small", sample(2)),
+        ("medium", sample(15)),
+        ("large", sample(60)),
+    ]
+}
+
+fn bin(c: &mut Criterion) {
+    let mut group = c.benchmark_group("bin");
+    for (name, spell) in samples() {
+        group.bench_with_input(BenchmarkId::from_parameter(name), &spell, |b, spell| {
+            b.iter(|| black_box(spell).bin())
+        });
+    }
+    group.finish();
+}
+
+fn decode(c: &mut Criterion) {
+    let mut group = c.benchmark_group("decode");
+    for (name, spell) in samples() {
+        let bin = spell.bin();
+        group.bench_with_input(BenchmarkId::from_parameter(name), &bin, |b, bin| {
+            b.iter(|| Spell::decode(black_box(bin)).unwrap())
+        });
+    }
+    group.finish();
+}
+
+/// [`Spell::decode`] against [`Spell::decode_ref`] on the same buffers, so the cost of
+/// allocating every key, comment and constant shows up side by side. The buffers keep
+/// their `psi:` prefixes, since `decode_ref` has to allocate keys that lack one.
+fn decode_ref(c: &mut Criterion) {
+    let mut group = c.benchmark_group("decode_ref");
+    for (name, spell) in samples() {
+        let bin = spell.bin_verbose();
+        group.bench_with_input(BenchmarkId::new("owned", name), &bin, |b, bin| {
+            b.iter(|| Spell::decode(black_box(bin)).unwrap())
+        });
+        group.bench_with_input(BenchmarkId::new("borrowed", name), &bin, |b, bin| {
+            b.iter(|| Spell::decode_ref(black_box(bin)).unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn encode_spell_bench(c: &mut Criterion) {
+    let mut group = c.benchmark_group("encode_spell");
+    for (name, spell) in samples() {
+        group.bench_with_input(BenchmarkId::from_parameter(name), &spell, |b, spell| {
+            b.iter(|| encode_spell(black_box(spell)).unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn decode_spell_bench(c: &mut Criterion) {
+    let mut group = c.benchmark_group("decode_spell");
+    for (name, spell) in samples() {
+        let encoded = encode_spell(&spell).unwrap();
+        group.bench_with_input(BenchmarkId::from_parameter(name), &encoded, |b, encoded| {
+            b.iter(|| decode_spell(black_box(encoded)).unwrap())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bin,
+    decode,
+    decode_ref,
+    encode_spell_bench,
+    decode_spell_bench
+);
+criterion_main!(benches);
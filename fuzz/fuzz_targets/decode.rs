@@ -0,0 +1,15 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use psi_spell_encode::Spell;
+
+// Arbitrary bytes must only ever produce an `Err`, never a panic, and anything that
+// does decode must be encodable again. `bin_verbose` is compared exactly: `bin` strips
+// `psi:` and so may change keys like `psi:psi:foo` or unqualified keys read from a
+// verbose buffer, but its output must still decode.
+fuzz_target!(|data: &[u8]| {
+    if let Ok(spell) = Spell::decode(data) {
+        assert_eq!(Spell::decode(&spell.bin_verbose()), Ok(spell.clone()));
+        assert!(Spell::decode(&spell.bin()).is_ok());
+    }
+});
@@ -12,23 +12,134 @@ export interface Piece {
   x: number
   y: number
 }
+/**
+ * The side of a piece that a parameter reads its input from.
+ *
+ * Parameter sides are stored as a raw byte in [`SpellParams`]; the discriminants here
+ * are the values Psi writes for each side.
+ */
+export const enum Side {
+  Off = 0,
+  Top = 1,
+  Bottom = 2,
+  Left = 3,
+  Right = 4
+}
 export interface SpellData {
   key: string
   params?: SpellParams
   constant?: string
   comment?: string
+  /**
+   * Tags this crate does not know about, kept so that SNBT and JSON round-trips do
+   * not drop them. They are not part of the binary format. `None` if there are
+   * none, so JavaScript callers can leave it out.
+   */
+  extra?: Record<string, any>
 }
 export function spellFromSnbt(snbt: string): Spell
+/** Reads a spell in Psi's own export format: base64 of the gzip-compressed binary NBT. */
+export function spellFromPsiExport(psiExport: string): Spell
+/** Writes a spell in Psi's own export format, the inverse of [`spell_from_psi_export`]. */
+export function spellToPsiExport(spell: Spell): string
 export function decodeSpellFromBytes(bytes: Array<number>): Spell
 export function encodeBytesToUrlSafe(bytes: Array<number>): string
+/** Like [`encode_bytes_to_url_safe`], but with an explicit gzip level from 0 (none) to 9 (best). */
+export function encodeBytesToUrlSafeWithLevel(bytes: Array<number>, level: number): string
 export function decodeUrlSafeToBytes(urlSafe: string): Array<number>
+/**
+ * Like [`decode_url_safe_to_bytes`], but fails instead of inflating more than
+ * `max_bytes`, so untrusted input cannot exhaust memory.
+ */
+export function decodeUrlSafeToBytesLimited(urlSafe: string, maxBytes: number): Array<number>
+export function encodeBytesToBase64(bytes: Array<number>): string
+export function decodeBase64ToBytes(base64: string): Array<number>
+/**
+ * Like [`encode_bytes_to_url_safe`], but as a raw DEFLATE stream, without gzip's
+ * header and checksum footer.
+ */
+export function encodeBytesToUrlSafeDeflate(bytes: Array<number>): string
+export function decodeUrlSafeDeflateToBytes(urlSafe: string): Array<number>
+/**
+ * Base64-encodes `bytes` without compressing them, for inspecting the binary format
+ * or for spells small enough that gzip would only make them longer.
+ */
+export function encodeBytesToBase64Raw(bytes: Array<number>): string
+export function decodeBase64RawToBytes(base64: string): Array<number>
+/**
+ * Decodes a string produced by any of the encoders in this crate, detecting the
+ * base64 alphabet and the compression codec from the data itself.
+ */
+export function decodeAnyToBytes(s: string): Array<number>
 export function encodeSpellToBytes(spell: Spell): Array<number>
 export function decodeSpell(urlSafe: string): Spell
+/** Reads only the name of the spell in a share string, without decoding its pieces. */
+export function peekNameFromUrl(urlSafe: string): string
+/**
+ * Encodes several spells into one share string. Compressing them together lets gzip
+ * reuse what the spells have in common.
+ */
+export function encodeSpells(spells: Array<Spell>): string
+/** Decodes a string produced by [`encode_spells`], keeping the spells in order. */
+export function decodeSpells(urlSafe: string): Array<Spell>
+/** Sizes at each stage of [`encode_spell_with_stats`], in bytes. */
+export interface EncodeStats {
+  /** Length of the [`Spell::bin`] buffer. */
+  rawLen: number
+  /** Length after gzip. */
+  compressedLen: number
+  /** Length of the base64 share string. */
+  finalLen: number
+}
+/** A share string together with its [`EncodeStats`]. */
+export interface EncodedSpell {
+  encoded: string
+  stats: EncodeStats
+}
+/** Like [`encode_spell`], but also reports how large the spell was at each stage. */
+export function encodeSpellWithStats(spell: Spell): EncodedSpell
 export function encodeSpell(spell: Spell): string
 export function spellToSnbt(spell: Spell): string
+/** Like `spellToSnbt`, but indented over multiple lines with compound keys sorted. */
+export function spellToSnbtPretty(spell: Spell): string
+export function spellToJson(spell: Spell): string
+export function spellFromJson(json: string): Spell
+/** Whether `name` is a parameter that is encoded as a single index byte. */
+export function isBuiltinParam(name: string): boolean
+/** Parameter names that are encoded as a single index byte instead of by name. */
+export function listBuiltinParams(): Array<string>
+export const enum IssueSeverity {
+  /** The spell cannot be encoded until this is fixed. */
+  Error = 0,
+  /** The spell encodes, but probably will not work as intended in game. */
+  Warning = 1
+}
+/** One problem found by [`Spell::issues`]. */
+export interface ValidationIssue {
+  severity: IssueSeverity
+  /** Stable, snake_case identifier for the kind of issue. */
+  code: string
+  message: string
+  /** Position of the offending piece, if the issue concerns one. */
+  x?: number
+  y?: number
+}
+/** Every error and warning found in the spell, for showing in an editor. */
+export function validateSpell(spell: Spell): Array<ValidationIssue>
+/** Who made a spell and when, carried by [`Spell::meta`]. */
+export interface SpellMeta {
+  author: string
+  /** Seconds since the Unix epoch. Signed, since Node-API has no plain `u64`. */
+  createdUnix: number
+}
 export class Spell {
   mods: Array<Mod>
   pieces: Array<Piece>
   name: string
-  constructor(mods: Array<Mod>, pieces: Array<Piece>, name: string)
+  /**
+   * Only stored by [`Spell::bin`]. Psi has no field for it, so it is left out of
+   * JSON, SNBT and Psi's export.
+   */
+  meta?: SpellMeta
+  constructor(mods: Array<Mod>, pieces: Array<Piece>, name: string, meta?: SpellMeta | undefined | null)
 }
@@ -252,14 +252,37 @@ if (!nativeBinding) {
   throw new Error(`Failed to load native binding`)
 }
 
-const { Spell, spellFromSnbt, decodeSpellFromBytes, encodeBytesToUrlSafe, decodeUrlSafeToBytes, encodeSpellToBytes, decodeSpell, encodeSpell, spellToSnbt } = nativeBinding
+const { Spell, Side, spellFromSnbt, spellFromPsiExport, spellToPsiExport, decodeSpellFromBytes, encodeBytesToUrlSafe, encodeBytesToUrlSafeWithLevel, decodeUrlSafeToBytes, decodeUrlSafeToBytesLimited, encodeBytesToBase64, decodeBase64ToBytes, encodeBytesToUrlSafeDeflate, decodeUrlSafeDeflateToBytes, encodeBytesToBase64Raw, decodeBase64RawToBytes, decodeAnyToBytes, encodeSpellToBytes, decodeSpell, peekNameFromUrl, encodeSpells, decodeSpells, encodeSpellWithStats, encodeSpell, spellToSnbt, spellToSnbtPretty, spellToJson, spellFromJson, isBuiltinParam, listBuiltinParams, IssueSeverity, validateSpell } = nativeBinding
 
 module.exports.Spell = Spell
+module.exports.Side = Side
 module.exports.spellFromSnbt = spellFromSnbt
+module.exports.spellFromPsiExport = spellFromPsiExport
+module.exports.spellToPsiExport = spellToPsiExport
 module.exports.decodeSpellFromBytes = decodeSpellFromBytes
 module.exports.encodeBytesToUrlSafe = encodeBytesToUrlSafe
+module.exports.encodeBytesToUrlSafeWithLevel = encodeBytesToUrlSafeWithLevel
 module.exports.decodeUrlSafeToBytes = decodeUrlSafeToBytes
+module.exports.decodeUrlSafeToBytesLimited = decodeUrlSafeToBytesLimited
+module.exports.encodeBytesToBase64 = encodeBytesToBase64
+module.exports.decodeBase64ToBytes = decodeBase64ToBytes
+module.exports.encodeBytesToUrlSafeDeflate = encodeBytesToUrlSafeDeflate
+module.exports.decodeUrlSafeDeflateToBytes = decodeUrlSafeDeflateToBytes
+module.exports.encodeBytesToBase64Raw = encodeBytesToBase64Raw
+module.exports.decodeBase64RawToBytes = decodeBase64RawToBytes
+module.exports.decodeAnyToBytes = decodeAnyToBytes
 module.exports.encodeSpellToBytes = encodeSpellToBytes
 module.exports.decodeSpell = decodeSpell
+module.exports.peekNameFromUrl = peekNameFromUrl
+module.exports.encodeSpells = encodeSpells
+module.exports.decodeSpells = decodeSpells
+module.exports.encodeSpellWithStats = encodeSpellWithStats
 module.exports.encodeSpell = encodeSpell
 module.exports.spellToSnbt = spellToSnbt
+module.exports.spellToSnbtPretty = spellToSnbtPretty
+module.exports.spellToJson = spellToJson
+module.exports.spellFromJson = spellFromJson
+module.exports.isBuiltinParam = isBuiltinParam
+module.exports.listBuiltinParams = listBuiltinParams
+module.exports.IssueSeverity = IssueSeverity
+module.exports.validateSpell = validateSpell
@@ -0,0 +1,67 @@
+//! Encode, decode and inspect spells from the shell.
+//!
+//! The Node bindings cannot be linked into an executable, so build this with
+//! `cargo build --no-default-features --features cli`.
+
+use std::{
+    error::Error,
+    io::{self, Read},
+    process::ExitCode,
+};
+
+use clap::{Parser, Subcommand};
+use psi_spell_encode::{decode_spell, encode_spell, spell_from_snbt, Spell};
+
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Read a spell as JSON or SNBT on stdin and print its share string
+    Encode,
+    /// Read a share string on stdin and print the spell as JSON
+    Decode,
+    /// Read a share string on stdin and print a summary of the spell
+    Inspect,
+}
+
+fn run(cli: Cli) -> Result<(), Box<dyn Error>> {
+    let mut input = String::new();
+    io::stdin().read_to_string(&mut input)?;
+    let input = input.trim();
+
+    match cli.command {
+        Command::Encode => {
+            let spell = match Spell::from_json(input) {
+                Ok(spell) => spell,
+                Err(_) => spell_from_snbt(input)?,
+            };
+            println!("{}", encode_spell(&spell)?);
+        }
+        Command::Decode => println!("{}", decode_spell(input)?.to_json()?),
+        Command::Inspect => {
+            let spell = decode_spell(input)?;
+            println!("name: {}", spell.name);
+            for m in &spell.mods {
+                println!("mod: {} {}", m.name, m.version);
+            }
+            println!("pieces: {}", spell.pieces.len());
+        }
+    }
+
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    match run(Cli::parse()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,125 @@
+//! Chainable construction of [`Spell`]s.
+
+use crate::{Mod, Piece, Side, Spell, SpellData};
+
+/// Builds a [`Spell`] without spelling out every nested struct.
+///
+/// ```
+/// use psi_spell_encode::{Side, Spell};
+///
+/// let spell = Spell::builder()
+///     .name("Blink")
+///     .add_mod("psi", "2.0.0")
+///     .add_piece(0, 0, "psi:selector_caster")
+///     .add_piece(1, 0, "psi:trick_blink")
+///     .param("_target", Side::Left)
+///     .build();
+/// assert_eq!(spell.pieces.len(), 2);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct SpellBuilder {
+    name: String,
+    mods: Vec<Mod>,
+    pieces: Vec<Piece>,
+}
+
+impl SpellBuilder {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[inline]
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self
+    }
+
+    #[inline]
+    pub fn add_mod(mut self, name: impl Into<String>, version: impl Into<String>) -> Self {
+        self.mods.push(Mod {
+            name: name.into(),
+            version: version.into(),
+        });
+        self
+    }
+
+    /// Starts a piece at `(x, y)`. Chain [`PieceBuilder`] methods to fill it in.
+    #[inline]
+    pub fn add_piece(self, x: u8, y: u8, key: impl Into<String>) -> PieceBuilder {
+        PieceBuilder {
+            spell: self,
+            piece: Piece {
+                data: SpellData {
+                    key: key.into(),
+                    params: None,
+                    constant: None,
+                    comment: None,
+                    extra: None,
+                },
+                x,
+                y,
+            },
+        }
+    }
+
+    #[inline]
+    pub fn build(self) -> Spell {
+        Spell {
+            mods: self.mods,
+            pieces: self.pieces,
+            name: self.name,
+            meta: None,
+        }
+    }
+}
+
+/// A piece being added by [`SpellBuilder::add_piece`].
+#[derive(Debug, Clone)]
+pub struct PieceBuilder {
+    spell: SpellBuilder,
+    piece: Piece,
+}
+
+impl PieceBuilder {
+    #[inline]
+    pub fn param(mut self, name: impl Into<String>, side: Side) -> Self {
+        self.piece
+            .data
+            .params
+            .get_or_insert_with(Default::default)
+            .insert(name.into(), side.as_u8());
+        self
+    }
+
+    #[inline]
+    pub fn constant(mut self, value: impl Into<String>) -> Self {
+        self.piece.data.constant = Some(value.into());
+        self
+    }
+
+    #[inline]
+    pub fn comment(mut self, text: impl Into<String>) -> Self {
+        self.piece.data.comment = Some(text.into());
+        self
+    }
+
+    /// Finishes this piece and returns to the spell.
+    #[inline]
+    pub fn done(mut self) -> SpellBuilder {
+        self.spell.pieces.push(self.piece);
+        self.spell
+    }
+
+    /// Finishes this piece and starts another.
+    #[inline]
+    pub fn add_piece(self, x: u8, y: u8, key: impl Into<String>) -> PieceBuilder {
+        self.done().add_piece(x, y, key)
+    }
+
+    /// Finishes this piece and the spell.
+    #[inline]
+    pub fn build(self) -> Spell {
+        self.done().build()
+    }
+}
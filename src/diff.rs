@@ -0,0 +1,269 @@
+//! Structural comparison of two spells.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{normalize_key, Mod, Piece, Spell, SpellData};
+
+/// Changes needed to turn one spell into another, as produced by [`Spell::diff`].
+///
+/// Pieces are matched by position. A piece that disappears from one cell while an
+/// identical piece appears in another is reported as moved rather than as a removal
+/// plus an addition.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct SpellDiff {
+    /// `(old, new)` if the name changed.
+    pub name: Option<(String, String)>,
+    pub added_mods: Vec<Mod>,
+    pub removed_mods: Vec<Mod>,
+    pub added: Vec<Piece>,
+    pub removed: Vec<Piece>,
+    pub moved: Vec<PieceMove>,
+    pub changed: Vec<PieceChange>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct PieceMove {
+    pub from: (u8, u8),
+    pub to: (u8, u8),
+    pub data: SpellData,
+}
+
+/// A cell whose piece kept its position but changed its key, params, constant or
+/// comment.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct PieceChange {
+    pub x: u8,
+    pub y: u8,
+    pub old: SpellData,
+    pub new: SpellData,
+}
+
+impl SpellDiff {
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// A [`SpellDiff`] that does not apply cleanly, returned by [`Spell::apply_patch`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PatchError {
+    /// The patch renames the spell from a name it does not currently have.
+    NameMismatch { expected: String, found: String },
+    /// The patch removes a mod the spell does not declare.
+    MissingMod(Mod),
+    /// The patch removes, moves or changes a piece that is not at `(x, y)`.
+    MissingPiece { x: u8, y: u8 },
+    /// The patch adds or moves a piece onto a cell that is already occupied.
+    Occupied { x: u8, y: u8 },
+}
+
+impl fmt::Display for PatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NameMismatch { expected, found } => {
+                write!(f, "patch expects spell name {expected:?}, found {found:?}")
+            }
+            Self::MissingMod(m) => write!(f, "patch removes missing mod {}", m.name),
+            Self::MissingPiece { x, y } => write!(f, "patch expects a piece at ({x}, {y})"),
+            Self::Occupied { x, y } => write!(f, "patch places a piece on occupied ({x}, {y})"),
+        }
+    }
+}
+
+impl std::error::Error for PatchError {}
+
+/// Removes and returns the piece at `(x, y)` holding exactly `data`.
+fn take_piece(spell: &mut Spell, x: u8, y: u8, data: &SpellData) -> Result<Piece, PatchError> {
+    let i = spell
+        .pieces
+        .iter()
+        .position(|p| p.x == x && p.y == y && p.data == *data)
+        .ok_or(PatchError::MissingPiece { x, y })?;
+    Ok(spell.pieces.remove(i))
+}
+
+/// The first piece at each position.
+fn cells(spell: &Spell) -> BTreeMap<(u8, u8), &SpellData> {
+    let mut cells = BTreeMap::new();
+    for piece in &spell.pieces {
+        cells.entry((piece.x, piece.y)).or_insert(&piece.data);
+    }
+    cells
+}
+
+/// The `(x, y, normalized key)` of every piece, for [`Spell::similarity`].
+fn triples(spell: &Spell) -> BTreeSet<(u8, u8, String)> {
+    spell
+        .pieces
+        .iter()
+        .map(|piece| {
+            let key = normalize_key(&piece.data.key).into_owned();
+            (piece.x, piece.y, key)
+        })
+        .collect()
+}
+
+impl Spell {
+    /// Reports what changed between `self` and `other`.
+    pub fn diff(&self, other: &Spell) -> SpellDiff {
+        let mut diff = SpellDiff::default();
+
+        if self.name != other.name {
+            diff.name = Some((self.name.clone(), other.name.clone()));
+        }
+        diff.added_mods = other
+            .mods
+            .iter()
+            .filter(|m| !self.mods.contains(m))
+            .cloned()
+            .collect();
+        diff.removed_mods = self
+            .mods
+            .iter()
+            .filter(|m| !other.mods.contains(m))
+            .cloned()
+            .collect();
+
+        let old = cells(self);
+        let new = cells(other);
+        let mut added: Vec<_> = new
+            .iter()
+            .filter(|(pos, _)| !old.contains_key(*pos))
+            .map(|(&pos, &data)| (pos, data))
+            .collect();
+
+        for (&(x, y), &data) in &old {
+            match new.get(&(x, y)) {
+                Some(&new_data) if new_data != data => diff.changed.push(PieceChange {
+                    x,
+                    y,
+                    old: data.clone(),
+                    new: new_data.clone(),
+                }),
+                Some(_) => {}
+                None => {
+                    if let Some(i) = added.iter().position(|(_, added)| *added == data) {
+                        let (to, _) = added.remove(i);
+                        diff.moved.push(PieceMove {
+                            from: (x, y),
+                            to,
+                            data: data.clone(),
+                        });
+                    } else {
+                        diff.removed.push(Piece {
+                            data: data.clone(),
+                            x,
+                            y,
+                        });
+                    }
+                }
+            }
+        }
+
+        diff.added = added
+            .into_iter()
+            .map(|((x, y), data)| Piece {
+                data: data.clone(),
+                x,
+                y,
+            })
+            .collect();
+
+        diff
+    }
+
+    /// Applies `patch` as produced by [`Spell::diff`]. Nothing is changed if any part
+    /// of it conflicts with the current spell.
+    pub fn apply_patch(&mut self, patch: &SpellDiff) -> Result<(), PatchError> {
+        let mut spell = self.clone();
+
+        if let Some((old, new)) = &patch.name {
+            if spell.name != *old {
+                return Err(PatchError::NameMismatch {
+                    expected: old.clone(),
+                    found: spell.name,
+                });
+            }
+            spell.name = new.clone();
+        }
+        for m in &patch.removed_mods {
+            let i = spell
+                .mods
+                .iter()
+                .position(|existing| existing == m)
+                .ok_or_else(|| PatchError::MissingMod(m.clone()))?;
+            spell.mods.remove(i);
+        }
+        for m in &patch.added_mods {
+            if !spell.mods.contains(m) {
+                spell.mods.push(m.clone());
+            }
+        }
+
+        for piece in &patch.removed {
+            take_piece(&mut spell, piece.x, piece.y, &piece.data)?;
+        }
+        let mut moved = Vec::with_capacity(patch.moved.len());
+        for m in &patch.moved {
+            let mut piece = take_piece(&mut spell, m.from.0, m.from.1, &m.data)?;
+            (piece.x, piece.y) = m.to;
+            moved.push(piece);
+        }
+        for change in &patch.changed {
+            let (x, y) = (change.x, change.y);
+            let piece = spell
+                .pieces
+                .iter_mut()
+                .find(|p| p.x == x && p.y == y && p.data == change.old)
+                .ok_or(PatchError::MissingPiece { x, y })?;
+            piece.data = change.new.clone();
+        }
+        for piece in patch.added.iter().cloned().chain(moved) {
+            if spell
+                .pieces
+                .iter()
+                .any(|p| p.x == piece.x && p.y == piece.y)
+            {
+                let (x, y) = (piece.x, piece.y);
+                return Err(PatchError::Occupied { x, y });
+            }
+            spell.pieces.push(piece);
+        }
+
+        *self = spell;
+        Ok(())
+    }
+
+    /// Jaccard similarity of the two spells' `(x, y, key)` triples, with unqualified
+    /// keys counted under `psi:`: the number of triples both spells have, divided by
+    /// the number either has. Symmetric, 1.0 for spells with the same pieces in the same
+    /// places (including two empty spells) and 0.0 when no piece matches.
+    ///
+    /// ```
+    /// use psi_spell_encode::Spell;
+    ///
+    /// let a = Spell::builder()
+    ///     .add_piece(0, 0, "psi:selector_caster")
+    ///     .add_piece(1, 0, "psi:trick_blink")
+    ///     .build();
+    /// let b = Spell::builder().add_piece(1, 0, "psi:trick_blink").build();
+    /// assert_eq!(a.similarity(&b), 0.5);
+    /// ```
+    pub fn similarity(&self, other: &Spell) -> f64 {
+        let (a, b) = (triples(self), triples(other));
+        let union = a.union(&b).count();
+        if union == 0 {
+            return 1.0;
+        }
+        a.intersection(&b).count() as f64 / union as f64
+    }
+}
@@ -0,0 +1,131 @@
+//! Reading and writing spells on disk.
+
+use std::{
+    fmt, fs,
+    io::{self, BufRead},
+    path::Path,
+};
+
+use crate::{
+    decode_spell, encode_spell, spell_from_snbt, spell_to_snbt, NbtError, Spell, SpellError,
+    SpellParseError,
+};
+
+/// Error from [`Spell::from_file`], [`Spell::to_file`] or [`decode_spells_from_lines`].
+#[derive(Debug)]
+pub enum FileError {
+    /// The file could not be read or written.
+    Io(io::Error),
+    /// The extension is not one of `.json`, `.snbt` or `.txt`.
+    UnsupportedExtension,
+    Json(serde_json::Error),
+    Nbt(NbtError),
+    /// The contents were not a valid share string.
+    Parse(SpellParseError),
+    /// The spell cannot be encoded as a share string.
+    Invalid(SpellError),
+}
+
+impl fmt::Display for FileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "{e}"),
+            Self::UnsupportedExtension => {
+                write!(f, "spell files must end in .json, .snbt or .txt")
+            }
+            Self::Json(e) => write!(f, "{e}"),
+            Self::Nbt(e) => write!(f, "{e}"),
+            Self::Parse(e) => write!(f, "{e}"),
+            Self::Invalid(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl std::error::Error for FileError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            Self::UnsupportedExtension => None,
+            Self::Json(e) => Some(e),
+            Self::Nbt(e) => Some(e),
+            Self::Parse(e) => Some(e),
+            Self::Invalid(e) => Some(e),
+        }
+    }
+}
+
+impl From<io::Error> for FileError {
+    #[inline]
+    fn from(value: io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+enum Format {
+    Json,
+    Snbt,
+    ShareString,
+}
+
+impl Format {
+    fn of(path: &Path) -> Result<Self, FileError> {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => Ok(Self::Json),
+            Some("snbt") => Ok(Self::Snbt),
+            Some("txt") => Ok(Self::ShareString),
+            _ => Err(FileError::UnsupportedExtension),
+        }
+    }
+}
+
+impl Spell {
+    /// Reads a spell from a `.json`, `.snbt` or `.txt` (share string) file.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, FileError> {
+        let path = path.as_ref();
+        let format = Format::of(path)?;
+        let contents = fs::read_to_string(path)?;
+        let contents = contents.trim();
+
+        match format {
+            Format::Json => Spell::from_json(contents).map_err(FileError::Json),
+            Format::Snbt => spell_from_snbt(contents).map_err(FileError::Nbt),
+            Format::ShareString => decode_spell(contents).map_err(FileError::Parse),
+        }
+    }
+
+    /// Writes the spell in the format implied by the extension of `path`, as in
+    /// [`Spell::from_file`].
+    pub fn to_file<P: AsRef<Path>>(&self, path: P) -> Result<(), FileError> {
+        let path = path.as_ref();
+        let contents = match Format::of(path)? {
+            Format::Json => self.to_json().map_err(FileError::Json)?,
+            Format::Snbt => spell_to_snbt(self).map_err(FileError::Nbt)?,
+            Format::ShareString => encode_spell(self).map_err(FileError::Invalid)?,
+        };
+        Ok(fs::write(path, contents)?)
+    }
+}
+
+/// Lazily decodes one share string per line of `reader`, skipping blank lines, so
+/// large dumps can be processed without reading them into memory first.
+///
+/// ```
+/// use psi_spell_encode::{decode_spells_from_lines, encode_spell, Spell};
+///
+/// let a = encode_spell(&Spell::empty("A")).unwrap();
+/// let b = encode_spell(&Spell::empty("B")).unwrap();
+/// let dump = format!("{a}\n\n{b}\n");
+/// let spells: Vec<_> = decode_spells_from_lines(dump.as_bytes())
+///     .collect::<Result<_, _>>()
+///     .unwrap();
+/// assert_eq!(spells, [Spell::empty("A"), Spell::empty("B")]);
+/// ```
+pub fn decode_spells_from_lines<R: BufRead>(
+    reader: R,
+) -> impl Iterator<Item = Result<Spell, FileError>> {
+    reader.lines().filter_map(|line| match line {
+        Ok(line) if line.trim().is_empty() => None,
+        Ok(line) => Some(decode_spell(line.trim()).map_err(FileError::Parse)),
+        Err(e) => Some(Err(FileError::Io(e))),
+    })
+}
@@ -0,0 +1,966 @@
+//! The compact binary encoding behind [`Spell::bin`] and [`Spell::decode`].
+
+use std::{
+    borrow::Cow,
+    io::{self, Write},
+};
+
+use flate2::{Crc, CrcWriter};
+
+use crate::{
+    builtin_param_index, DecodeError, Mod, Piece, Spell, SpellData, SpellMeta, SpellParams,
+    BUILTIN_PARAMS,
+};
+
+/// Slice cursor used by the decoder. Reads borrow from the input.
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    #[inline]
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    #[inline]
+    fn is_empty(&self) -> bool {
+        self.pos >= self.data.len()
+    }
+
+    #[inline]
+    fn peek(&self) -> Option<u8> {
+        self.data.get(self.pos).copied()
+    }
+
+    #[inline]
+    fn next(&mut self) -> Result<u8, DecodeError> {
+        let b = *self.data.get(self.pos).ok_or(DecodeError::UnexpectedEof)?;
+        self.pos += 1;
+        Ok(b)
+    }
+
+    #[inline]
+    fn take(&mut self, len: usize) -> Result<&'a [u8], DecodeError> {
+        if self.data.len() - self.pos < len {
+            return Err(DecodeError::UnexpectedEof);
+        }
+        let out = &self.data[self.pos..self.pos + len];
+        self.pos += len;
+        Ok(out)
+    }
+
+    /// Reads up to and past the next `byte`, returning what came before it.
+    #[inline]
+    fn until(&mut self, byte: u8) -> Result<&'a [u8], DecodeError> {
+        let rest = &self.data[self.pos..];
+        let len = rest
+            .iter()
+            .position(|b| *b == byte)
+            .ok_or(DecodeError::UnexpectedEof)?;
+        self.pos += len + 1;
+        Ok(&rest[..len])
+    }
+
+    #[inline]
+    fn until_nul(&mut self) -> Result<&'a [u8], DecodeError> {
+        self.until(0)
+    }
+
+    /// Reads a LEB128 length.
+    #[inline]
+    fn len(&mut self) -> Result<usize, DecodeError> {
+        let mut len = 0usize;
+        for shift in (0..usize::BITS).step_by(7) {
+            let b = self.next()?;
+            len |= ((b & 0x7f) as usize) << shift;
+            if b & 0x80 == 0 {
+                return Ok(len);
+            }
+        }
+        Err(DecodeError::InvalidLength)
+    }
+
+    #[inline]
+    fn prefixed(&mut self) -> Result<&'a [u8], DecodeError> {
+        let len = self.len()?;
+        self.take(len)
+    }
+}
+
+#[inline]
+fn str_ref<'a>(b: &'a [u8], field: &'static str) -> Result<&'a str, DecodeError> {
+    std::str::from_utf8(b).map_err(|_| DecodeError::InvalidUtf8 { field })
+}
+
+#[inline]
+fn string(b: &[u8], field: &'static str) -> Result<String, DecodeError> {
+    str_ref(b, field).map(str::to_owned)
+}
+
+/// The params to encode for `data`. An empty map is written like `None`, so that a
+/// constant next to it is kept.
+#[inline]
+fn params_of(data: &SpellData) -> Option<&SpellParams> {
+    data.params.as_ref().filter(|params| !params.is_empty())
+}
+
+#[inline]
+fn write_len<W: Write>(w: &mut W, mut len: usize) -> io::Result<()> {
+    loop {
+        let b = (len & 0x7f) as u8;
+        len >>= 7;
+        if len == 0 {
+            return w.write_all(&[b]);
+        }
+        w.write_all(&[b | 0x80])?;
+    }
+}
+
+/// Bytes taken by [`write_len`] for `len`.
+#[inline]
+fn len_len(len: usize) -> usize {
+    let bits = (usize::BITS - len.leading_zeros()) as usize;
+    ((bits + 6) / 7).max(1)
+}
+
+impl Spell {
+    /// Version byte written at the start of every [`Spell::bin`] buffer.
+    ///
+    /// Buffers from before the version byte existed start directly with the spell
+    /// name, which is never an ASCII control character, so leading bytes in
+    /// `0x01..=0x1f` are reserved for format versions.
+    ///
+    /// - `1`: the spell name and comments are NUL-terminated.
+    /// - `2`: the spell name and comments are prefixed with their LEB128 length, so
+    ///   they may contain NUL.
+    /// - `3`: a little-endian CRC32 of every preceding byte is appended, so truncated or
+    ///   corrupted buffers fail with [`DecodeError::ChecksumMismatch`].
+    /// - `4`: a flags byte follows the version byte. With the wide coordinates flag set,
+    ///   each piece position takes two bytes, `x` then `y`, instead of one packed byte,
+    ///   so pieces beyond [`Spell::MAX_COORDINATE`] can be encoded. With the verbose keys
+    ///   flag set, piece keys are written and read back exactly as given; see
+    ///   [`Spell::bin_verbose`]. With the metadata flag set, the mod list is followed by
+    ///   the [`Spell::meta`] author, length-prefixed, and the creation time as a
+    ///   little-endian `i64`.
+    pub const FORMAT_VERSION: u8 = 4;
+
+    /// Whether any piece lies beyond [`Spell::MAX_COORDINATE`], needing the wide
+    /// position encoding.
+    #[inline]
+    fn needs_wide_coords(&self) -> bool {
+        self.pieces
+            .iter()
+            .any(|piece| piece.x > Self::MAX_COORDINATE || piece.y > Self::MAX_COORDINATE)
+    }
+
+    /// Encodes the spell in the current [`Spell::FORMAT_VERSION`].
+    ///
+    /// Only the first [`Spell::MAX_PARAMS`] params of a piece are written, since
+    /// larger counts would read back as a data marker. Use [`Spell::try_bin`] to get
+    /// [`crate::SpellError::TooManyParams`] instead.
+    #[inline]
+    pub fn bin(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        self.bin_into(&mut out);
+        out
+    }
+
+    /// Like [`Spell::bin`], but writes into `out`, replacing its contents and reusing
+    /// its allocation.
+    ///
+    /// ```
+    /// use psi_spell_encode::Spell;
+    ///
+    /// let spell = Spell::builder().add_piece(0, 0, "psi:trick_blink").build();
+    /// let mut out = vec![1, 2, 3];
+    /// spell.bin_into(&mut out);
+    /// assert_eq!(out, spell.bin());
+    /// ```
+    pub fn bin_into(&self, out: &mut Vec<u8>) {
+        out.clear();
+        out.reserve(self.bin_len());
+        self.write_bin(out).unwrap();
+    }
+
+    /// Length of [`Spell::bin`]'s output, computed without encoding.
+    pub fn bin_len(&self) -> usize {
+        let mut len = 2 + len_len(self.name.len()) + self.name.len() + 4;
+        if self.needs_wide_coords() {
+            len += self.pieces.len();
+        }
+        if let Some(meta) = &self.meta {
+            len += len_len(meta.author.len()) + meta.author.len() + 8;
+        }
+
+        len += self
+            .mods
+            .iter()
+            .map(|m| m.name.len() + 1 + m.version.len())
+            .sum::<usize>();
+        len += self.mods.len().saturating_sub(1) + 1;
+
+        for piece in &self.pieces {
+            let data = &piece.data;
+            let key = data.key.as_bytes();
+            let key = key.strip_prefix(b"psi:").unwrap_or(key);
+            len += 1 + key.len() + 1;
+            let comment = data.comment.as_ref().map_or(0, String::len);
+            len += len_len(comment) + comment;
+
+            if let Some(params) = params_of(data) {
+                len += 1;
+                for key in params.keys().take(Self::MAX_PARAMS) {
+                    len += match builtin_param_index(key) {
+                        Some(_) => 2,
+                        None => 1 + key.len() + 1 + 1,
+                    };
+                }
+            } else if let Some(constant) = &data.constant {
+                len += 1 + constant.len() + 1;
+            } else {
+                len += 1;
+            }
+        }
+
+        len
+    }
+
+    /// Like [`Spell::bin`], but piece keys are written in full instead of having `psi:`
+    /// stripped, and [`Spell::decode`] reads them back without adding `psi:` to
+    /// unqualified ones. For tools that need the literal keys.
+    ///
+    /// ```
+    /// use psi_spell_encode::Spell;
+    ///
+    /// let spell = Spell::builder()
+    ///     .add_piece(0, 0, "psi:foo")
+    ///     .add_piece(1, 0, "bar")
+    ///     .build();
+    /// let bin = spell.bin_verbose();
+    /// assert_eq!(Spell::decode(&bin).unwrap(), spell);
+    /// ```
+    pub fn bin_verbose(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(self.bin_len() + 4 * self.pieces.len());
+        self.write_bin_as(&mut out, true).unwrap();
+        out
+    }
+
+    /// Writes the same bytes as [`Spell::bin`] straight into `w`.
+    #[inline]
+    pub fn write_bin<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        self.write_bin_as(w, false)
+    }
+
+    fn write_bin_as<W: Write>(&self, w: &mut W, verbose_keys: bool) -> io::Result<()> {
+        let layout = Layout {
+            length_prefixed: true,
+            wide_coords: self.needs_wide_coords(),
+            verbose_keys,
+            meta: self.meta.is_some(),
+        };
+        let mut w = CrcWriter::new(w);
+        w.write_all(&[Self::FORMAT_VERSION, layout.flags()])?;
+        self.write_body(&mut w, layout)?;
+        let sum = w.crc().sum();
+        w.into_inner().write_all(&sum.to_le_bytes())
+    }
+
+    fn write_body<W: Write>(&self, w: &mut W, layout: Layout) -> io::Result<()> {
+        write_len(w, self.name.len())?;
+        w.write_all(self.name.as_bytes())?;
+
+        for (i, m) in self.mods.iter().enumerate() {
+            if i > 0 {
+                w.write_all(b";")?;
+            }
+            w.write_all(m.name.as_bytes())?;
+            w.write_all(b",")?;
+            w.write_all(m.version.as_bytes())?;
+        }
+        w.write_all(b"]")?;
+        if let Some(meta) = &self.meta {
+            write_len(w, meta.author.len())?;
+            w.write_all(meta.author.as_bytes())?;
+            w.write_all(&meta.created_unix.to_le_bytes())?;
+        }
+
+        for piece in &self.pieces {
+            let data = &piece.data;
+            let key = data.key.as_bytes();
+            let key = if layout.verbose_keys {
+                key
+            } else {
+                key.strip_prefix(b"psi:").unwrap_or(key)
+            };
+            let params = params_of(data);
+            let constant = &data.constant;
+            let comment = data.comment.as_deref().unwrap_or_default();
+            if layout.wide_coords {
+                w.write_all(&[piece.x, piece.y])?;
+            } else {
+                w.write_all(&[piece.x << 4 | (piece.y & 0b1111)])?;
+            }
+            w.write_all(key)?;
+            w.write_all(&[0])?;
+            write_len(w, comment.len())?;
+            w.write_all(comment.as_bytes())?;
+
+            if let Some(params) = params {
+                let count = params.len().min(Self::MAX_PARAMS);
+                w.write_all(&[count as u8])?;
+                for (key, side) in params.iter().take(count) {
+                    if let Some(pos) = builtin_param_index(key) {
+                        w.write_all(&[pos])?;
+                    } else {
+                        w.write_all(&[255])?;
+                        w.write_all(key.as_bytes())?;
+                        w.write_all(&[0])?;
+                    }
+                    w.write_all(&[*side])?;
+                }
+            } else if let Some(constant) = constant {
+                w.write_all(&[255])?;
+                w.write_all(constant.as_bytes())?;
+                w.write_all(&[0])?;
+            } else {
+                w.write_all(&[254])?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Decodes a buffer written by [`Spell::bin`] with this or any earlier
+    /// [`Spell::FORMAT_VERSION`].
+    ///
+    /// An empty buffer fails with [`DecodeError::TruncatedHeader`], and an empty mod
+    /// list decodes as no mods rather than one mod with an empty name.
+    ///
+    /// ```
+    /// use psi_spell_encode::Spell;
+    ///
+    /// let spell = Spell::builder()
+    ///     .name("Blink")
+    ///     .add_piece(0, 0, "psi:trick_blink")
+    ///     .build();
+    /// assert_eq!(Spell::decode(&spell.bin()).unwrap(), spell);
+    /// ```
+    #[inline]
+    pub fn decode(data: &[u8]) -> Result<Self, DecodeError> {
+        Self::decode_with_default_namespace(data, "psi")
+    }
+
+    /// Like [`Spell::decode`], but unqualified piece keys are put in `namespace`
+    /// instead of `psi`, for buffers from encoders that strip another namespace.
+    ///
+    /// ```
+    /// use psi_spell_encode::Spell;
+    ///
+    /// let bin = Spell::builder().add_piece(0, 0, "psi:trick_blink").build().bin();
+    /// let phi = Spell::decode_with_default_namespace(&bin, "phi").unwrap();
+    /// assert_eq!(phi.pieces[0].data.key, "phi:trick_blink");
+    /// ```
+    pub fn decode_with_default_namespace(
+        data: &[u8],
+        namespace: &str,
+    ) -> Result<Self, DecodeError> {
+        let options = Options {
+            default_namespace: Some(namespace),
+        };
+        let (body, version) = split_version(data)?;
+        decode_body(body, version, options)
+    }
+
+    /// Like [`Spell::decode`], but piece keys are left exactly as stored, so
+    /// unqualified keys stay unqualified.
+    ///
+    /// [`Spell::bin`] strips `psi:` from keys, so its `psi` pieces come back
+    /// unqualified too. Decode buffers from [`Spell::bin_verbose`] to tell a literal
+    /// `foo` apart from `psi:foo`; those always keep their keys.
+    ///
+    /// ```
+    /// use psi_spell_encode::Spell;
+    ///
+    /// let bin = Spell::builder().add_piece(0, 0, "foo").build().bin();
+    /// let preserved = Spell::decode_preserving_keys(&bin).unwrap();
+    /// assert_eq!(preserved.pieces[0].data.key, "foo");
+    /// ```
+    pub fn decode_preserving_keys(data: &[u8]) -> Result<Self, DecodeError> {
+        let options = Options {
+            default_namespace: None,
+        };
+        let (body, version) = split_version(data)?;
+        decode_body(body, version, options)
+    }
+
+    /// Like [`Spell::decode`], but decodes into `spell`, reusing the allocations of its
+    /// name and piece and mod lists. On error, `spell` holds whatever was decoded
+    /// before the failure.
+    ///
+    /// ```
+    /// use psi_spell_encode::Spell;
+    ///
+    /// let a = Spell::builder().name("A").add_piece(0, 0, "psi:trick_blink").build();
+    /// let b = Spell::builder().name("B").add_mod("phi", "1.0").build();
+    /// let mut scratch = Spell::empty("");
+    /// for spell in [&a, &b] {
+    ///     Spell::decode_into(&spell.bin(), &mut scratch).unwrap();
+    ///     assert_eq!(&scratch, spell);
+    /// }
+    /// ```
+    pub fn decode_into(data: &[u8], spell: &mut Spell) -> Result<(), DecodeError> {
+        spell.name.clear();
+        spell.mods.clear();
+        spell.pieces.clear();
+        spell.meta = None;
+
+        let (body, version) = split_version(data)?;
+        decode_body_into(body, version, Options::default(), spell)
+    }
+
+    /// Like [`Spell::decode`], but strings are borrowed from `data` where possible
+    /// instead of copied, for callers that only read the spell. Convert the view into
+    /// a [`Spell`] with `Spell::from` to keep it.
+    ///
+    /// ```
+    /// use psi_spell_encode::Spell;
+    ///
+    /// let spell = Spell::builder()
+    ///     .name("Blink")
+    ///     .add_piece(0, 0, "psi:trick_blink")
+    ///     .build();
+    /// let bin = spell.bin();
+    /// let view = Spell::decode_ref(&bin).unwrap();
+    /// assert_eq!(view.name, "Blink");
+    /// assert_eq!(Spell::from(view), spell);
+    /// ```
+    pub fn decode_ref(data: &[u8]) -> Result<SpellView<'_>, DecodeError> {
+        let (body, version) = split_version(data)?;
+        let mut reader = Reader::new(body);
+        let layout = read_layout(&mut reader, version)?;
+
+        let mut view = SpellView {
+            name: read_name(&mut reader, layout)?,
+            mods: Vec::with_capacity(4),
+            pieces: Vec::with_capacity(body.len() / MIN_PIECE_BYTES),
+            meta: None,
+        };
+        for m in read_mods(&mut reader)? {
+            view.mods.push(m?);
+        }
+        if layout.meta {
+            view.meta = Some(read_meta(&mut reader)?);
+        }
+
+        let options = Options::default();
+        while !reader.is_empty() {
+            let piece = read_piece_view(&mut reader, layout, options)?;
+            view.pieces.push(piece);
+        }
+        Ok(view)
+    }
+
+    /// Like [`Spell::decode`], but also rejects spells that [`Spell::validate`] would
+    /// refuse to encode, and spells whose piece namespaces are not all declared in
+    /// [`Spell::mods`].
+    ///
+    /// ```
+    /// use psi_spell_encode::{DecodeError, Spell};
+    ///
+    /// let spell = Spell::builder()
+    ///     .name("Stray")
+    ///     .add_piece(0, 0, "psi:selector_caster")
+    ///     .add_piece(1, 0, "magical_psi:trick_conjure_star")
+    ///     .build();
+    /// let bin = spell.bin();
+    ///
+    /// assert!(Spell::decode(&bin).is_ok());
+    /// assert_eq!(
+    ///     Spell::decode_strict(&bin),
+    ///     Err(DecodeError::UndeclaredMod("magical_psi".to_owned()))
+    /// );
+    /// ```
+    pub fn decode_strict(data: &[u8]) -> Result<Self, DecodeError> {
+        let spell = Self::decode(data)?;
+        spell.validate()?;
+        if let Some(ns) = spell.missing_mod_declarations().into_iter().next() {
+            return Err(DecodeError::UndeclaredMod(ns));
+        }
+        Ok(spell)
+    }
+
+    /// Decodes as much of `data` as possible, for salvaging damaged buffers. Decoding
+    /// stops at the first piece that cannot be read; the pieces before it are kept and
+    /// the error is returned alongside them.
+    ///
+    /// A failed checksum is reported but does not stop decoding, since a truncated
+    /// buffer has lost its checksum along with its last pieces. The last four bytes are
+    /// still taken to be the checksum, rather than decoded as piece data.
+    ///
+    /// ```
+    /// use psi_spell_encode::Spell;
+    ///
+    /// let spell = Spell::builder()
+    ///     .add_piece(0, 0, "psi:selector_caster")
+    ///     .add_piece(1, 0, "psi:trick_blink")
+    ///     .build();
+    /// let bin = spell.bin();
+    /// // Cut into the second piece's key, dropping the checksum too.
+    /// let (partial, errors) = Spell::decode_partial(&bin[..bin.len() - 12]);
+    /// assert_eq!(partial.pieces, spell.pieces[..1]);
+    /// assert_eq!(errors.len(), 2);
+    /// ```
+    pub fn decode_partial(data: &[u8]) -> (Self, Vec<DecodeError>) {
+        let mut errors = Vec::new();
+        let (body, version) = match data.first() {
+            Some(&version @ 1..=2) => (&data[1..], version),
+            Some(&version @ 3..=Self::FORMAT_VERSION) => match verify_checksum(data) {
+                Ok(body) => (body, version),
+                Err(e) => {
+                    errors.push(e);
+                    let end = if data.len() >= 1 + 4 {
+                        data.len() - 4
+                    } else {
+                        data.len()
+                    };
+                    (&data[1..end], version)
+                }
+            },
+            Some(&version @ 0x01..=0x1f) => {
+                errors.push(DecodeError::UnsupportedVersion(version));
+                return (Spell::empty(""), errors);
+            }
+            _ => (data, 1),
+        };
+        let (spell, error) = decode_body_partial(body, version, Options::default());
+        errors.extend(error);
+        (spell, errors)
+    }
+
+    /// Reads only the spell name from a buffer in any format [`Spell::decode`]
+    /// accepts, without decoding the mods or pieces. The checksum is not verified.
+    ///
+    /// ```
+    /// use psi_spell_encode::Spell;
+    ///
+    /// let bin = Spell::builder()
+    ///     .name("Blink")
+    ///     .add_piece(0, 0, "psi:trick_blink")
+    ///     .build()
+    ///     .bin();
+    /// assert_eq!(Spell::peek_name(&bin).unwrap(), "Blink");
+    /// ```
+    pub fn peek_name(data: &[u8]) -> Result<String, DecodeError> {
+        let (body, version) = match data.first() {
+            Some(&version @ 1..=Self::FORMAT_VERSION) => (&data[1..], version),
+            Some(&version @ 0x01..=0x1f) => return Err(DecodeError::UnsupportedVersion(version)),
+            _ => (data, 1),
+        };
+        let mut reader = Reader::new(body);
+        let layout = read_layout(&mut reader, version)?;
+        read_name(&mut reader, layout).map(str::to_owned)
+    }
+
+    /// Decodes a buffer written before [`Spell::FORMAT_VERSION`] was introduced.
+    ///
+    /// Buffers whose encoder left out the NUL after an empty comment are accepted
+    /// for pieces holding a constant or no data. Pieces with params cannot be told
+    /// apart from a comment and still need the NUL.
+    ///
+    /// ```
+    /// use psi_spell_encode::Spell;
+    ///
+    /// let data = b"Blink\0]\x00trick_blink\0\0\xfe";
+    /// let spell = Spell::decode_legacy(data).unwrap();
+    /// assert_eq!(spell.pieces[0].data.key, "psi:trick_blink");
+    /// ```
+    #[inline]
+    pub fn decode_legacy(data: &[u8]) -> Result<Self, DecodeError> {
+        // The unversioned layout is the same as version 1.
+        decode_body(data, 1, Options::default())
+    }
+}
+
+/// Splits off the version byte, returning the body and the version it was written
+/// with. The checksum of version 3+ buffers is verified and stripped.
+fn split_version(data: &[u8]) -> Result<(&[u8], u8), DecodeError> {
+    match data.first() {
+        Some(&version @ 1..=2) => Ok((&data[1..], version)),
+        Some(&version @ 3..=Spell::FORMAT_VERSION) => Ok((verify_checksum(data)?, version)),
+        Some(&version @ 0x01..=0x1f) => Err(DecodeError::UnsupportedVersion(version)),
+        _ => Ok((data, 1)),
+    }
+}
+
+/// Checks the CRC32 trailer of a version 3+ buffer, returning the bytes between the
+/// version byte and the trailer.
+fn verify_checksum(data: &[u8]) -> Result<&[u8], DecodeError> {
+    if data.len() < 1 + 4 {
+        return Err(DecodeError::TruncatedHeader);
+    }
+    let (payload, trailer) = data.split_at(data.len() - 4);
+    let expected = u32::from_le_bytes([trailer[0], trailer[1], trailer[2], trailer[3]]);
+    let mut crc = Crc::new();
+    crc.update(payload);
+    if crc.sum() != expected {
+        return Err(DecodeError::ChecksumMismatch);
+    }
+    Ok(&payload[1..])
+}
+
+/// Writes `spells` as a LEB128 count followed by each [`Spell::bin`] buffer, itself
+/// prefixed with its LEB128 length.
+pub(crate) fn write_spells<W: Write>(spells: &[Spell], w: &mut W) -> io::Result<()> {
+    write_len(w, spells.len())?;
+    for spell in spells {
+        write_len(w, spell.bin_len())?;
+        spell.write_bin(w)?;
+    }
+    Ok(())
+}
+
+/// Reads the container written by [`write_spells`].
+pub(crate) fn read_spells(data: &[u8]) -> Result<Vec<Spell>, DecodeError> {
+    let mut reader = Reader::new(data);
+    let count = reader.len()?;
+    // Every entry takes at least its length byte, so a count larger than the input
+    // is truncated data rather than a reason to allocate.
+    if count > data.len() {
+        return Err(DecodeError::UnexpectedEof);
+    }
+    let mut spells = Vec::with_capacity(count);
+    for _ in 0..count {
+        spells.push(Spell::decode(reader.prefixed()?)?);
+    }
+    Ok(spells)
+}
+
+/// A spell decoded by [`Spell::decode_ref`], borrowing its strings from the buffer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpellView<'a> {
+    pub name: &'a str,
+    /// `(name, version)` of each required mod.
+    pub mods: Vec<(&'a str, &'a str)>,
+    pub pieces: Vec<PieceView<'a>>,
+    pub meta: Option<SpellMeta>,
+}
+
+/// A piece of a [`SpellView`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PieceView<'a> {
+    pub x: u8,
+    pub y: u8,
+    /// Only owned when the buffer left out the `psi:` namespace.
+    pub key: Cow<'a, str>,
+    pub comment: Option<&'a str>,
+    /// `(name, side)` in buffer order. Builtin names point into the static table;
+    /// custom names are owned, so converting the view into a [`Piece`] moves them
+    /// rather than copying.
+    pub params: Option<Vec<(Cow<'static, str>, u8)>>,
+    pub constant: Option<&'a str>,
+}
+
+impl From<PieceView<'_>> for Piece {
+    fn from(value: PieceView<'_>) -> Self {
+        let params = value.params.map(|params| {
+            params
+                .into_iter()
+                .map(|(name, side)| (name.into_owned(), side))
+                .collect::<SpellParams>()
+        });
+        let data = SpellData {
+            key: value.key.into_owned(),
+            params,
+            constant: value.constant.map(str::to_owned),
+            comment: value.comment.map(str::to_owned),
+            extra: None,
+        };
+        Piece {
+            data,
+            x: value.x,
+            y: value.y,
+        }
+    }
+}
+
+impl From<SpellView<'_>> for Spell {
+    fn from(value: SpellView<'_>) -> Self {
+        Spell {
+            mods: value
+                .mods
+                .into_iter()
+                .map(|(name, version)| Mod {
+                    name: name.to_owned(),
+                    version: version.to_owned(),
+                })
+                .collect(),
+            pieces: value.pieces.into_iter().map(Piece::from).collect(),
+            name: value.name.to_owned(),
+            meta: value.meta,
+        }
+    }
+}
+
+/// Header flag: piece positions are two bytes, `x` then `y`.
+const WIDE_COORDS: u8 = 0b0000_0001;
+/// Header flag: piece keys are stored as given, with no implied namespace.
+const VERBOSE_KEYS: u8 = 0b0000_0010;
+/// Header flag: a [`SpellMeta`] follows the mod list.
+const META: u8 = 0b0000_0100;
+/// Every flag this version of the crate understands.
+const KNOWN_FLAGS: u8 = WIDE_COORDS | VERBOSE_KEYS | META;
+
+/// How the parts of a buffer are laid out, from its version and flags.
+#[derive(Clone, Copy)]
+struct Layout {
+    length_prefixed: bool,
+    wide_coords: bool,
+    verbose_keys: bool,
+    meta: bool,
+}
+
+impl Layout {
+    /// The header flags byte describing this layout.
+    #[inline]
+    fn flags(&self) -> u8 {
+        let mut flags = 0;
+        if self.wide_coords {
+            flags |= WIDE_COORDS;
+        }
+        if self.verbose_keys {
+            flags |= VERBOSE_KEYS;
+        }
+        if self.meta {
+            flags |= META;
+        }
+        flags
+    }
+}
+
+/// Choices the caller makes about how a buffer is decoded.
+#[derive(Clone, Copy)]
+struct Options<'a> {
+    /// Namespace given to piece keys without one, or `None` to leave them as stored.
+    default_namespace: Option<&'a str>,
+}
+
+impl Default for Options<'_> {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            default_namespace: Some("psi"),
+        }
+    }
+}
+
+/// Fewest bytes a piece can take: the position, the key's NUL, the comment's length
+/// (or NUL) and the data marker. Bounds the piece count of a buffer.
+const MIN_PIECE_BYTES: usize = 4;
+
+/// Decodes everything after the version byte, laid out as `version` describes.
+fn decode_body(data: &[u8], version: u8, options: Options<'_>) -> Result<Spell, DecodeError> {
+    match decode_body_partial(data, version, options) {
+        (spell, None) => Ok(spell),
+        (_, Some(e)) => Err(e),
+    }
+}
+
+/// Decodes as much of the body as possible, returning the spell so far and the error
+/// that stopped decoding, if any.
+fn decode_body_partial(
+    data: &[u8],
+    version: u8,
+    options: Options<'_>,
+) -> (Spell, Option<DecodeError>) {
+    let mut spell = Spell {
+        name: String::new(),
+        mods: Vec::with_capacity(4),
+        pieces: Vec::with_capacity(data.len() / MIN_PIECE_BYTES),
+        meta: None,
+    };
+    let error = decode_body_into(data, version, options, &mut spell).err();
+    (spell, error)
+}
+
+/// Decodes the body into `spell`, which must already be empty. Pieces decoded before
+/// an error are kept.
+fn decode_body_into(
+    data: &[u8],
+    version: u8,
+    options: Options<'_>,
+    spell: &mut Spell,
+) -> Result<(), DecodeError> {
+    let mut reader = Reader::new(data);
+    let layout = read_layout(&mut reader, version)?;
+    read_header(&mut reader, layout, spell)?;
+    while !reader.is_empty() {
+        let piece = read_piece(&mut reader, layout, options)?;
+        spell.pieces.push(piece);
+    }
+
+    Ok(())
+}
+
+fn read_layout(reader: &mut Reader<'_>, version: u8) -> Result<Layout, DecodeError> {
+    let flags = if version >= 4 {
+        reader.next().map_err(|_| DecodeError::TruncatedHeader)?
+    } else {
+        0
+    };
+    if flags & !KNOWN_FLAGS != 0 {
+        return Err(DecodeError::UnsupportedFlags(flags));
+    }
+    Ok(Layout {
+        length_prefixed: version >= 2,
+        wide_coords: flags & WIDE_COORDS != 0,
+        verbose_keys: flags & VERBOSE_KEYS != 0,
+        meta: flags & META != 0,
+    })
+}
+
+fn read_header(
+    reader: &mut Reader<'_>,
+    layout: Layout,
+    spell: &mut Spell,
+) -> Result<(), DecodeError> {
+    spell.name.push_str(read_name(reader, layout)?);
+    for m in read_mods(reader)? {
+        let (name, version) = m?;
+        spell.mods.push(Mod {
+            name: name.to_owned(),
+            version: version.to_owned(),
+        });
+    }
+    if layout.meta {
+        spell.meta = Some(read_meta(reader)?);
+    }
+
+    Ok(())
+}
+
+fn read_name<'a>(reader: &mut Reader<'a>, layout: Layout) -> Result<&'a str, DecodeError> {
+    let name = if layout.length_prefixed {
+        reader.prefixed()
+    } else {
+        reader.until_nul()
+    };
+    let name = name.map_err(|_| DecodeError::TruncatedHeader)?;
+    str_ref(name, "spell name")
+}
+
+/// Reads the `]`-terminated mod list, returning the `(name, version)` of each mod in
+/// it. A mod without a `,` has an empty version.
+fn read_mods<'a>(
+    reader: &mut Reader<'a>,
+) -> Result<impl Iterator<Item = Result<(&'a str, &'a str), DecodeError>>, DecodeError> {
+    let mods = reader
+        .until(b']')
+        .map_err(|_| DecodeError::TruncatedHeader)?;
+    Ok(mods
+        .split(|b| *b == b';')
+        .filter(|m| !m.is_empty())
+        .map(|m| -> Result<_, DecodeError> {
+            let (name, version) = match m.iter().position(|b| *b == b',') {
+                Some(i) => (&m[..i], &m[i + 1..]),
+                None => (m, &[][..]),
+            };
+            Ok((str_ref(name, "mod name")?, str_ref(version, "mod version")?))
+        }))
+}
+
+fn read_meta(reader: &mut Reader<'_>) -> Result<SpellMeta, DecodeError> {
+    let author = reader
+        .prefixed()
+        .map_err(|_| DecodeError::TruncatedHeader)?;
+    let created = reader.take(8).map_err(|_| DecodeError::TruncatedHeader)?;
+    Ok(SpellMeta {
+        author: string(author, "author")?,
+        created_unix: i64::from_le_bytes(created.try_into().unwrap()),
+    })
+}
+
+#[inline]
+fn read_piece(
+    reader: &mut Reader<'_>,
+    layout: Layout,
+    options: Options<'_>,
+) -> Result<Piece, DecodeError> {
+    read_piece_view(reader, layout, options).map(Piece::from)
+}
+
+fn read_piece_view<'a>(
+    reader: &mut Reader<'a>,
+    layout: Layout,
+    options: Options<'_>,
+) -> Result<PieceView<'a>, DecodeError> {
+    let (x, y) = if layout.wide_coords {
+        (reader.next()?, reader.next()?)
+    } else {
+        let xy = reader.next()?;
+        (xy >> 4, xy & 0b1111)
+    };
+    let key = str_ref(reader.until_nul()?, "piece key")?;
+    let key = match options.default_namespace {
+        Some(namespace) if !layout.verbose_keys && !key.contains(':') => {
+            Cow::Owned(format!("{namespace}:{key}"))
+        }
+        _ => Cow::Borrowed(key),
+    };
+
+    let comment = if layout.length_prefixed {
+        reader.prefixed()?
+    } else if matches!(reader.peek(), Some(254 | 255)) {
+        // Some old encoders skip the NUL of an empty comment. The data markers are
+        // never valid UTF-8, so they cannot be the start of a real comment.
+        &[]
+    } else {
+        reader.until_nul()?
+    };
+    let comment = if comment.is_empty() {
+        None
+    } else {
+        Some(str_ref(comment, "comment")?)
+    };
+
+    let mut params = Vec::new();
+    let mut constant = None;
+
+    let ty = reader.next()?;
+    if ty == 255 {
+        constant = Some(str_ref(reader.until_nul()?, "constant")?);
+    } else if ty != 254 {
+        let len = ty;
+        params.reserve(len as usize);
+        for _ in 0..len {
+            let type_or_pos = reader.next()?;
+            let param_key = if type_or_pos == 255 {
+                Cow::Owned(string(reader.until_nul()?, "param name")?)
+            } else {
+                let name = BUILTIN_PARAMS
+                    .get(type_or_pos as usize)
+                    .ok_or(DecodeError::BadParamIndex(type_or_pos))?;
+                Cow::Borrowed(*name)
+            };
+
+            let side = reader.next()?;
+            params.push((param_key, side));
+        }
+    }
+
+    let params = if params.is_empty() {
+        None
+    } else {
+        Some(params)
+    };
+
+    Ok(PieceView {
+        x,
+        y,
+        key,
+        comment,
+        params,
+        constant,
+    })
+}
@@ -0,0 +1,111 @@
+//! Reporting everything wrong with a spell at once, for editors.
+
+use crate::{GridSpec, Spell, SpellError};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "napi", napi)]
+pub enum IssueSeverity {
+    /// The spell cannot be encoded until this is fixed.
+    Error = 0,
+    /// The spell encodes, but probably will not work as intended in game.
+    Warning = 1,
+}
+
+/// One problem found by [`Spell::issues`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "napi", napi(object))]
+pub struct ValidationIssue {
+    pub severity: IssueSeverity,
+    /// Stable, snake_case identifier for the kind of issue.
+    pub code: String,
+    pub message: String,
+    /// Position of the offending piece, if the issue concerns one.
+    pub x: Option<u8>,
+    pub y: Option<u8>,
+}
+
+impl ValidationIssue {
+    fn warning(code: &str, message: String, pos: Option<(u8, u8)>) -> Self {
+        Self {
+            severity: IssueSeverity::Warning,
+            code: code.to_owned(),
+            message,
+            x: pos.map(|(x, _)| x),
+            y: pos.map(|(_, y)| y),
+        }
+    }
+}
+
+impl From<SpellError> for ValidationIssue {
+    fn from(value: SpellError) -> Self {
+        let (code, pos) = match &value {
+            SpellError::OverlappingPieces { x, y } => ("overlapping_pieces", Some((*x, *y))),
+            SpellError::TooManyParams { x, y, .. } => ("too_many_params", Some((*x, *y))),
+            SpellError::ReservedByteInMod { .. } => ("reserved_byte_in_mod", None),
+            SpellError::NulInField { x, y, .. } => ("nul_in_field", Some((*x, *y))),
+            SpellError::OutOfGrid { x, y } => ("outside_grid", Some((*x, *y))),
+            SpellError::InvalidSide { x, y, .. } => ("invalid_side", Some((*x, *y))),
+        };
+        Self {
+            severity: IssueSeverity::Error,
+            code: code.to_owned(),
+            message: value.to_string(),
+            x: pos.map(|(x, _)| x),
+            y: pos.map(|(_, y)| y),
+        }
+    }
+}
+
+impl Spell {
+    /// Every error [`Spell::validate`] would report, followed by warnings for a name
+    /// longer than [`Spell::MAX_NAME_LEN`], params that are not connected to any side
+    /// and namespaces without a mod declaration.
+    ///
+    /// ```
+    /// use psi_spell_encode::{IssueSeverity, Side, Spell};
+    ///
+    /// let spell = Spell::builder()
+    ///     .add_piece(0, 0, "psi:trick_blink")
+    ///     .param("_target", Side::Off)
+    ///     .build();
+    /// let issues = spell.issues();
+    /// assert_eq!(issues[0].severity, IssueSeverity::Warning);
+    /// assert_eq!(issues[0].code, "unconnected_param");
+    /// ```
+    pub fn issues(&self) -> Vec<ValidationIssue> {
+        let mut issues: Vec<ValidationIssue> = self
+            .errors(Some(GridSpec::default()))
+            .into_iter()
+            .map(ValidationIssue::from)
+            .collect();
+
+        let len = self.name.chars().count();
+        if len > Self::MAX_NAME_LEN {
+            let message = format!(
+                "name has {len} characters, Psi accepts at most {}",
+                Self::MAX_NAME_LEN
+            );
+            issues.push(ValidationIssue::warning("name_too_long", message, None));
+        }
+
+        for piece in &self.pieces {
+            let pos = Some((piece.x, piece.y));
+            for (name, side) in piece.data.params.iter().flatten() {
+                if *side == 0 {
+                    let message = format!(
+                        "param {name} of the piece at ({}, {}) is not connected",
+                        piece.x, piece.y
+                    );
+                    issues.push(ValidationIssue::warning("unconnected_param", message, pos));
+                }
+            }
+        }
+
+        for ns in self.missing_mod_declarations() {
+            let message = format!("pieces use mod {ns:?}, which is not declared");
+            issues.push(ValidationIssue::warning("undeclared_mod", message, None));
+        }
+
+        issues
+    }
+}
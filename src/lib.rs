@@ -1,42 +1,84 @@
+#[cfg(feature = "napi")]
 #[macro_use]
 extern crate napi_derive;
 
+mod builder;
+mod diff;
+mod file;
+mod format;
+mod issues;
+#[cfg(feature = "napi")]
+mod node;
+mod qr;
+#[cfg(feature = "testing")]
+pub mod testing;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+pub use builder::{PieceBuilder, SpellBuilder};
+pub use diff::{PatchError, PieceChange, PieceMove, SpellDiff};
+pub use file::{decode_spells_from_lines, FileError};
+pub use format::{PieceView, SpellView};
+pub use issues::{IssueSeverity, ValidationIssue};
+pub use qr::QrError;
+pub use quartz_nbt::{NbtCompound, NbtTag};
+
 use std::{
-    collections::HashMap,
-    io::{BufRead, Cursor, Read},
-    ops::Deref,
+    borrow::Cow,
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fmt,
+    io::{self, Cursor, Read, Write},
+    ops::RangeInclusive,
+    str::FromStr,
 };
 
-use flate2::read::{GzDecoder, GzEncoder};
-use napi::{bindgen_prelude::Utf16String, Status};
+use flate2::{
+    read::{DeflateDecoder, GzDecoder},
+    write::{DeflateEncoder, GzEncoder},
+};
+use indexmap::IndexMap;
 use quartz_nbt::{io::Flavor, serde::deserialize_from_buffer};
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 #[serde(rename_all = "camelCase")]
-#[napi(constructor)]
+#[cfg_attr(feature = "napi", napi(constructor))]
 pub struct Spell {
-    #[serde(rename = "modsRequired")]
+    #[serde(rename = "modsRequired", alias = "mods_required")]
     pub mods: Vec<Mod>,
-    #[serde(rename = "spellList")]
+    /// Also read from `spell_list`, as written by forks of Psi that use snake_case keys.
+    #[serde(rename = "spellList", alias = "spell_list")]
     pub pieces: Vec<Piece>,
-    #[serde(rename = "spellName")]
+    #[serde(rename = "spellName", alias = "spell_name")]
     pub name: String,
+    /// Only stored by [`Spell::bin`]. Psi has no field for it, so it is left out of
+    /// JSON, SNBT and Psi's export.
+    #[serde(skip)]
+    pub meta: Option<SpellMeta>,
 }
 
-#[derive(Serialize, Deserialize, Clone, PartialEq, Eq)]
+/// Who made a spell and when, carried by [`Spell::meta`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "napi", napi(object))]
+pub struct SpellMeta {
+    pub author: String,
+    /// Seconds since the Unix epoch. Signed, since Node-API has no plain `u64`.
+    pub created_unix: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 #[serde(rename_all = "camelCase")]
-#[napi(object)]
+#[cfg_attr(feature = "napi", napi(object))]
 pub struct Mod {
-    #[serde(rename = "modName")]
+    #[serde(rename = "modName", alias = "mod_name")]
     pub name: String,
-    #[serde(rename = "modVersion")]
+    #[serde(rename = "modVersion", alias = "mod_version")]
     pub version: String,
 }
 
-#[derive(Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 #[serde(rename_all = "camelCase")]
-#[napi(object)]
+#[cfg_attr(feature = "napi", napi(object))]
 pub struct Piece {
     pub data: SpellData,
     pub x: u8,
@@ -89,213 +131,1228 @@ const BUILTIN_PARAMS: [&str; 43] = [
     "_ray_start",
 ];
 
-pub type SpellParams = HashMap<String, u8>;
+/// `key` with the implied `psi:` namespace added if it has none.
+#[inline]
+fn normalize_key(key: &str) -> Cow<'_, str> {
+    if key.contains(':') {
+        Cow::Borrowed(key)
+    } else {
+        Cow::Owned(format!("psi:{key}"))
+    }
+}
+
+/// Parameter names that are encoded as a single index byte instead of by name.
+#[inline]
+pub fn builtin_params() -> &'static [&'static str] {
+    &BUILTIN_PARAMS
+}
+
+/// Whether `name` is one of [`builtin_params`].
+///
+/// ```
+/// use psi_spell_encode::is_builtin_param;
+///
+/// assert!(is_builtin_param("_target"));
+/// assert!(!is_builtin_param("_custom"));
+/// ```
+#[inline]
+pub fn is_builtin_param(name: &str) -> bool {
+    BUILTIN_PARAMS.contains(&name)
+}
+
+/// Returns the index byte used to encode `name`, if it is a builtin parameter.
+#[inline]
+pub fn builtin_param_index(name: &str) -> Option<u8> {
+    BUILTIN_PARAMS
+        .iter()
+        .position(|e| *e == name)
+        .map(|pos| pos as u8)
+}
+
+/// Parameter name to connected side. Insertion order is kept through encoding and
+/// decoding, matching the order Psi lists the connections in.
+pub type SpellParams = IndexMap<String, u8>;
+
+/// Shapes that `params` is found in across exports: an object, or an array of
+/// `[name, side]` pairs.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ParamsRepr {
+    Map(SpellParams),
+    Pairs(Vec<(String, u8)>),
+}
+
+fn deserialize_params<'de, D>(deserializer: D) -> Result<Option<SpellParams>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    Ok(
+        Option::<ParamsRepr>::deserialize(deserializer)?.map(|params| match params {
+            ParamsRepr::Map(params) => params,
+            ParamsRepr::Pairs(pairs) => pairs.into_iter().collect(),
+        }),
+    )
+}
+
+/// A flattened field is always deserialized, as an empty map if no tags are left over,
+/// so that case is mapped to `None` here.
+fn deserialize_extra<'de, D>(
+    deserializer: D,
+) -> Result<Option<IndexMap<String, serde_json::Value>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let extra = IndexMap::deserialize(deserializer)?;
+    Ok(if extra.is_empty() { None } else { Some(extra) })
+}
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 #[serde(rename_all = "camelCase")]
-#[napi(object)]
+#[cfg_attr(feature = "napi", napi(object))]
 pub struct SpellData {
     pub key: String,
+    /// Read from either an object or an array of `[name, side]` pairs, always written
+    /// as an object.
+    ///
+    /// An empty map means the same as `None`: the binary format stores both as a piece
+    /// without params, and decodes them as `None`.
+    #[serde(
+        default,
+        deserialize_with = "deserialize_params",
+        skip_serializing_if = "Option::is_none"
+    )]
     pub params: Option<SpellParams>,
-    #[serde(rename = "constant_value")]
+    #[serde(
+        rename = "constant_value",
+        alias = "constantValue",
+        skip_serializing_if = "Option::is_none"
+    )]
     pub constant: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub comment: Option<String>,
+    /// Tags this crate does not know about, kept so that SNBT and JSON round-trips do
+    /// not drop them. They are not part of the binary format. `None` if there are
+    /// none, so JavaScript callers can leave it out.
+    ///
+    /// Values are stored as JSON, so NBT tag types are not kept: integers of any width
+    /// are written back as long tags and floats as double tags.
+    #[serde(flatten, deserialize_with = "deserialize_extra")]
+    pub extra: Option<IndexMap<String, serde_json::Value>>,
 }
 
-impl Spell {
+/// The side of a piece that a parameter reads its input from.
+///
+/// Parameter sides are stored as a raw byte in [`SpellParams`]; the discriminants here
+/// are the values Psi writes for each side.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "napi", napi)]
+pub enum Side {
+    Off = 0,
+    Top = 1,
+    Bottom = 2,
+    Left = 3,
+    Right = 4,
+}
+
+impl Side {
+    pub const ALL: [Side; 5] = [Side::Off, Side::Top, Side::Bottom, Side::Left, Side::Right];
+
+    #[inline]
+    pub fn from_u8(value: u8) -> Option<Self> {
+        Self::ALL.get(value as usize).copied()
+    }
+
     #[inline]
-    pub fn bin(&self) -> Vec<u8> {
-        let mut out: Vec<u8> = Vec::new();
+    pub fn as_u8(self) -> u8 {
+        self as u8
+    }
+}
+
+/// A constant piece's value, interpreted from its string form.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConstantValue {
+    Number(f64),
+    Vector3([f64; 3]),
+    /// Anything that is neither a number nor an `x,y,z` vector.
+    Raw(String),
+}
+
+/// Parses a finite number. Psi's constants cannot hold infinities or NaN, so the
+/// spellings Rust accepts for them are left as raw text.
+#[inline]
+fn parse_finite(value: &str) -> Option<f64> {
+    value.trim().parse().ok().filter(|n: &f64| n.is_finite())
+}
+
+impl ConstantValue {
+    pub fn parse(value: &str) -> Self {
+        if let Some(n) = parse_finite(value) {
+            return Self::Number(n);
+        }
+
+        let mut parts = value.split(',').map(parse_finite);
+        if let (Some(Some(x)), Some(Some(y)), Some(Some(z)), None) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
         {
-            let name = self.name.as_bytes();
-            out.extend_from_slice(name);
-            out.push(0);
+            return Self::Vector3([x, y, z]);
         }
 
-        for m in &self.mods {
-            let name = m.name.as_bytes();
-            let version = m.version.as_bytes();
-            out.extend_from_slice(name);
-            out.push(b',');
-            out.extend_from_slice(version);
-            out.push(b';');
+        Self::Raw(value.to_owned())
+    }
+}
+
+/// What a piece stores besides its key and comment, from [`SpellData::kind`]. Each
+/// kind has its own marker in the binary format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PieceKind {
+    /// Neither params nor a constant, like a connector. Written as the `254` marker.
+    Empty,
+    /// A constant value. Written as the `255` marker followed by the value.
+    Constant,
+    /// At least one param. Written as the param count followed by the params.
+    Connected,
+}
+
+impl SpellData {
+    /// Which of [`PieceKind`] this data is encoded as. Params win over a constant, and
+    /// an empty params map counts as none.
+    ///
+    /// ```
+    /// use psi_spell_encode::{PieceKind, Side, Spell};
+    ///
+    /// let spell = Spell::builder()
+    ///     .add_piece(0, 0, "psi:connector")
+    ///     .add_piece(1, 0, "psi:constant_number")
+    ///     .constant("4")
+    ///     .add_piece(2, 0, "psi:trick_blink")
+    ///     .param("_target", Side::Left)
+    ///     .build();
+    /// let kinds: Vec<_> = spell.pieces.iter().map(|p| p.data.kind()).collect();
+    /// assert_eq!(kinds, [PieceKind::Empty, PieceKind::Constant, PieceKind::Connected]);
+    /// ```
+    pub fn kind(&self) -> PieceKind {
+        if matches!(&self.params, Some(params) if !params.is_empty()) {
+            PieceKind::Connected
+        } else if self.constant.is_some() {
+            PieceKind::Constant
+        } else {
+            PieceKind::Empty
+        }
+    }
+
+    #[inline]
+    pub fn parse_constant(&self) -> Option<ConstantValue> {
+        self.constant.as_deref().map(ConstantValue::parse)
+    }
+
+    /// The side `param` is connected to, or `None` if the parameter is unset or its
+    /// stored byte is not a known [`Side`].
+    #[inline]
+    pub fn side(&self, param: &str) -> Option<Side> {
+        self.params
+            .as_ref()?
+            .get(param)
+            .copied()
+            .and_then(Side::from_u8)
+    }
+
+    /// The part of [`SpellData::key`] before the `:`, or `psi` if the key is
+    /// unqualified.
+    ///
+    /// ```
+    /// use psi_spell_encode::Spell;
+    ///
+    /// let spell = Spell::builder()
+    ///     .add_piece(0, 0, "psi:foo")
+    ///     .add_piece(1, 0, "phi:bar")
+    ///     .add_piece(2, 0, "foo")
+    ///     .build();
+    /// let parts: Vec<_> = spell
+    ///     .pieces
+    ///     .iter()
+    ///     .map(|p| (p.data.namespace(), p.data.path()))
+    ///     .collect();
+    /// assert_eq!(parts, [("psi", "foo"), ("phi", "bar"), ("psi", "foo")]);
+    /// ```
+    #[inline]
+    pub fn namespace(&self) -> &str {
+        self.key
+            .split_once(':')
+            .map_or("psi", |(namespace, _)| namespace)
+    }
+
+    /// The part of [`SpellData::key`] after the `:`, or the whole key if it is
+    /// unqualified.
+    #[inline]
+    pub fn path(&self) -> &str {
+        self.key.split_once(':').map_or(&self.key, |(_, path)| path)
+    }
+
+    /// The builtin params that are connected to a side, with their raw side bytes, in
+    /// [`builtin_params`] order. Custom and unconnected params are left out.
+    ///
+    /// ```
+    /// use psi_spell_encode::{Side, Spell};
+    ///
+    /// let spell = Spell::builder()
+    ///     .add_piece(0, 0, "psi:trick_blink")
+    ///     .param("_custom", Side::Left)
+    ///     .param("_target", Side::Top)
+    ///     .build();
+    /// let connections = spell.pieces[0].data.builtin_connections();
+    /// assert_eq!(connections, [("_target", Side::Top as u8)]);
+    /// ```
+    pub fn builtin_connections(&self) -> Vec<(&'static str, u8)> {
+        let Some(params) = &self.params else {
+            return Vec::new();
+        };
+        BUILTIN_PARAMS
+            .iter()
+            .filter_map(|name| match params.get(*name) {
+                Some(&side) if side != 0 => Some((*name, side)),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecodeError {
+    /// The buffer ended in the middle of a piece.
+    UnexpectedEof,
+    /// A string field was not valid UTF-8. `field` names which one.
+    InvalidUtf8 { field: &'static str },
+    /// A parameter referenced a builtin index that does not exist.
+    BadParamIndex(u8),
+    /// The buffer ended before the spell name and mod list were read.
+    TruncatedHeader,
+    /// The buffer was written by a newer, unknown version of the format.
+    UnsupportedVersion(u8),
+    /// A length prefix did not fit in a `usize`.
+    InvalidLength,
+    /// The header sets flags this version of the crate does not understand.
+    UnsupportedFlags(u8),
+    /// The checksum stored in the buffer does not match its contents, usually because
+    /// the share string was truncated or altered.
+    ChecksumMismatch,
+    /// The spell was read, but failed [`Spell::validate`]. Only returned by
+    /// [`Spell::decode_strict`] and [`nbt_to_bin`].
+    Invalid(SpellError),
+    /// A piece uses a namespace with no matching mod declaration. Only returned by
+    /// [`Spell::decode_strict`].
+    UndeclaredMod(String),
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnexpectedEof => write!(f, "unexpected end of spell data"),
+            Self::InvalidUtf8 { field } => write!(f, "{field} is not valid UTF-8"),
+            Self::BadParamIndex(idx) => write!(f, "unknown builtin parameter index {idx}"),
+            Self::TruncatedHeader => write!(f, "spell header is truncated"),
+            Self::UnsupportedVersion(v) => write!(f, "unsupported spell format version {v}"),
+            Self::InvalidLength => write!(f, "length prefix is too large"),
+            Self::UnsupportedFlags(flags) => {
+                write!(f, "unsupported spell format flags {flags:#010b}")
+            }
+            Self::ChecksumMismatch => {
+                write!(
+                    f,
+                    "spell data is corrupted or truncated (checksum mismatch)"
+                )
+            }
+            Self::Invalid(e) => write!(f, "{e}"),
+            Self::UndeclaredMod(ns) => write!(f, "pieces use undeclared mod {ns:?}"),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Invalid(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<SpellError> for DecodeError {
+    #[inline]
+    fn from(value: SpellError) -> Self {
+        Self::Invalid(value)
+    }
+}
+
+/// A spell that cannot be represented faithfully in the binary format.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SpellError {
+    /// More than one piece occupies the same cell.
+    OverlappingPieces { x: u8, y: u8 },
+    /// A piece has more parameters than the count byte can express.
+    TooManyParams { x: u8, y: u8, count: usize },
+    /// A mod name or version contains one of the bytes that delimit the mod list.
+    ReservedByteInMod { name: String, byte: u8 },
+    /// A NUL-terminated field contains a NUL byte. `field` names which one.
+    NulInField { x: u8, y: u8, field: &'static str },
+    /// A piece lies outside the [`GridSpec`] the spell was validated against.
+    OutOfGrid { x: u8, y: u8 },
+    /// A param is connected to a side byte outside [`Spell::VALID_SIDES`].
+    InvalidSide {
+        x: u8,
+        y: u8,
+        param: String,
+        side: u8,
+    },
+}
+
+impl fmt::Display for SpellError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::OverlappingPieces { x, y } => {
+                write!(f, "more than one piece occupies ({x}, {y})")
+            }
+            Self::TooManyParams { x, y, count } => write!(
+                f,
+                "piece at ({x}, {y}) has {count} params, at most {} can be encoded",
+                Spell::MAX_PARAMS
+            ),
+            Self::ReservedByteInMod { name, byte } => write!(
+                f,
+                "mod {name:?} contains the reserved character {:?}",
+                *byte as char
+            ),
+            Self::NulInField { x, y, field } => {
+                write!(f, "{field} of the piece at ({x}, {y}) contains a NUL byte")
+            }
+            Self::OutOfGrid { x, y } => write!(f, "piece at ({x}, {y}) is outside the grid"),
+            Self::InvalidSide { x, y, param, side } => write!(
+                f,
+                "param {param} of the piece at ({x}, {y}) has the unknown side {side}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SpellError {}
+
+/// Size of the CAD grid that pieces must fit in, checked by [`Spell::validate_in`].
+///
+/// The default is vanilla Psi's 9x9 grid. Modpacks that enlarge the CAD can supply a
+/// larger one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GridSpec {
+    pub width: u8,
+    pub height: u8,
+}
+
+impl GridSpec {
+    /// The grid of an unmodified Psi CAD.
+    pub const VANILLA: Self = Self {
+        width: 9,
+        height: 9,
+    };
+
+    #[inline]
+    pub fn contains(&self, x: u8, y: u8) -> bool {
+        x < self.width && y < self.height
+    }
+}
+
+impl Default for GridSpec {
+    #[inline]
+    fn default() -> Self {
+        Self::VANILLA
+    }
+}
+
+impl Spell {
+    #[inline]
+    pub fn builder() -> SpellBuilder {
+        SpellBuilder::new()
+    }
+
+    /// A spell with no mods and no pieces.
+    ///
+    /// ```
+    /// use psi_spell_encode::Spell;
+    ///
+    /// let spell = Spell::empty("Blank");
+    /// assert!(spell.is_empty());
+    /// assert_eq!(Spell::decode(&spell.bin()).unwrap(), spell);
+    /// ```
+    #[inline]
+    pub fn empty(name: impl Into<String>) -> Self {
+        Self {
+            mods: Vec::new(),
+            pieces: Vec::new(),
+            name: name.into(),
+            meta: None,
+        }
+    }
+
+    /// Whether the spell has no pieces. Its name and mods are not considered.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.pieces.is_empty()
+    }
+
+    /// Serializes the spell as JSON. Unset optional piece fields are left out, as in
+    /// Psi's own export, and [`Spell::from_json`] accepts them either missing or `null`.
+    ///
+    /// ```
+    /// use psi_spell_encode::Spell;
+    ///
+    /// let spell = Spell::builder().add_piece(0, 0, "psi:trick_blink").build();
+    /// let json = spell.to_json().unwrap();
+    /// assert_eq!(Spell::from_json(&json).unwrap(), spell);
+    /// ```
+    #[inline]
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string(self)
+    }
+
+    #[inline]
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+
+    /// Highest x or y coordinate that fits in the packed position byte. Spells with
+    /// pieces beyond it are encoded with two position bytes per piece.
+    pub const MAX_COORDINATE: u8 = 0b1111;
+
+    /// Most params a single piece can have. The count shares its byte with the `254`
+    /// (no data) and `255` (constant) markers.
+    pub const MAX_PARAMS: usize = 253;
+
+    /// Longest name, in characters, that Psi accepts for a spell. Longer names encode
+    /// fine, but the game rejects the spell on import, so [`Spell::issues`] warns about
+    /// them.
+    pub const MAX_NAME_LEN: usize = 20;
+
+    /// Side bytes that Psi understands, the values of [`Side`]. Any byte encodes, but
+    /// one outside this range means the spell is corrupt or from a newer Psi.
+    pub const VALID_SIDES: RangeInclusive<u8> = Side::Off as u8..=Side::Right as u8;
+
+    /// The share string for this spell, as [`encode_spell`] produces. Parse it back with
+    /// [`str::parse`].
+    #[inline]
+    pub fn to_share_string(&self) -> Result<String, SpellError> {
+        encode_spell(self)
+    }
+
+    /// Checks that every piece can be encoded without losing information, that the
+    /// pieces fit in vanilla Psi's 9x9 grid and that every side byte is one of
+    /// [`Spell::VALID_SIDES`].
+    ///
+    /// ```
+    /// use psi_spell_encode::{Side, Spell, SpellError};
+    ///
+    /// let spell = Spell::builder()
+    ///     .add_piece(0, 0, "psi:trick_blink")
+    ///     .param("a\0b", Side::Left)
+    ///     .build();
+    /// let err = SpellError::NulInField { x: 0, y: 0, field: "param name" };
+    /// assert_eq!(spell.validate(), Err(err));
+    /// ```
+    #[inline]
+    pub fn validate(&self) -> Result<(), SpellError> {
+        self.validate_in(GridSpec::default())
+    }
+
+    /// Like [`Spell::validate`], but against a custom grid, for modpacks that enlarge
+    /// the CAD.
+    ///
+    /// ```
+    /// use psi_spell_encode::{GridSpec, Spell};
+    ///
+    /// let spell = Spell::builder().add_piece(12, 0, "psi:trick_blink").build();
+    /// let grid = GridSpec { width: 16, height: 16 };
+    /// assert_eq!(spell.validate_in(grid), Ok(()));
+    /// ```
+    pub fn validate_in(&self, grid: GridSpec) -> Result<(), SpellError> {
+        match self.errors(Some(grid)).into_iter().next() {
+            Some(e) => Err(e),
+            None => Ok(()),
         }
-        let last = out.len() - 1;
-        out[last] = b']';
+    }
 
+    /// Like [`Spell::validate`], but without the grid and side checks.
+    /// Encoders use this, since the format itself has none of these limits.
+    fn check_encodable(&self) -> Result<(), SpellError> {
+        match self.errors(None).into_iter().next() {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+
+    /// Every problem [`Spell::validate_in`] checks for, in the order it checks them.
+    /// The limits of the game are only checked if a `grid` is given.
+    fn errors(&self, grid: Option<GridSpec>) -> Vec<SpellError> {
+        let mut errors = Vec::new();
+        for m in &self.mods {
+            let reserved = m
+                .name
+                .bytes()
+                .chain(m.version.bytes())
+                .find(|b| matches!(b, b',' | b';' | b']'));
+            if let Some(byte) = reserved {
+                let name = m.name.clone();
+                errors.push(SpellError::ReservedByteInMod { name, byte });
+            }
+        }
         for piece in &self.pieces {
-            let data = &piece.data;
-            let key = data.key.as_bytes();
-            let key = if &key[0..4] == b"psi:" {
-                &key[4..]
-            } else {
-                key
-            };
-            let params = &data.params;
-            let constant = &data.constant;
-            let comment = &data.comment;
-            out.push(piece.x << 4 | (piece.y & 0b1111));
-            out.extend_from_slice(key);
-            out.push(0);
-            if let Some(comment) = comment {
-                out.extend_from_slice(comment.as_bytes());
+            let (x, y) = (piece.x, piece.y);
+            if matches!(grid, Some(grid) if !grid.contains(x, y)) {
+                errors.push(SpellError::OutOfGrid { x, y });
             }
-            out.push(0);
-
-            if let Some(params) = params {
-                out.push(params.len() as u8);
-                for (key, side) in params {
-                    if let Some(pos) = BUILTIN_PARAMS.iter().position(|e| **e == *key) {
-                        out.push(pos as u8);
-                    } else {
-                        out.push(255);
-                        out.extend_from_slice(key.as_bytes());
-                        out.push(0);
+            if let Some(params) = &piece.data.params {
+                if params.len() > Self::MAX_PARAMS {
+                    let count = params.len();
+                    errors.push(SpellError::TooManyParams { x, y, count });
+                }
+                if grid.is_some() {
+                    for (param, &side) in params {
+                        if !Self::VALID_SIDES.contains(&side) {
+                            let param = param.clone();
+                            errors.push(SpellError::InvalidSide { x, y, param, side });
+                        }
                     }
-                    out.push(*side);
                 }
-            } else if let Some(constant) = constant {
-                out.push(255);
-                out.extend_from_slice(constant.as_bytes());
-                out.push(0);
+            }
+            let data = &piece.data;
+            let nul = if data.key.contains('\0') {
+                Some("piece key")
+            } else if data.constant.as_deref().unwrap_or_default().contains('\0') {
+                Some("constant")
+            } else if data
+                .params
+                .iter()
+                .flatten()
+                .any(|(name, _)| name.contains('\0'))
+            {
+                Some("param name")
             } else {
-                out.push(254);
+                None
+            };
+            if let Some(field) = nul {
+                errors.push(SpellError::NulInField { x, y, field });
             }
         }
+        for (x, y) in self.overlapping_pieces() {
+            errors.push(SpellError::OverlappingPieces { x, y });
+        }
+        errors
+    }
 
-        out
+    /// Positions, in `(x, y)` order, that are occupied by more than one piece.
+    pub fn overlapping_pieces(&self) -> Vec<(u8, u8)> {
+        let mut counts = BTreeMap::new();
+        for piece in &self.pieces {
+            *counts.entry((piece.x, piece.y)).or_insert(0usize) += 1;
+        }
+        counts
+            .into_iter()
+            .filter(|(_, count)| *count > 1)
+            .map(|(pos, _)| pos)
+            .collect()
     }
 
-    #[inline]
-    pub fn decode(data: &[u8]) -> Self {
-        #[inline]
-        fn read_until<T>(cursor: &mut Cursor<T>, byte: u8) -> Vec<u8>
-        where
-            T: std::convert::AsRef<[u8]>,
-        {
-            let mut out = Vec::new();
-            cursor.read_until(byte, &mut out).unwrap();
-            out.pop();
-            out
+    /// Every distinct key namespace used by the pieces, counting unqualified keys as `psi`.
+    pub fn namespaces(&self) -> BTreeSet<String> {
+        self.pieces
+            .iter()
+            .map(|piece| piece.data.namespace().to_owned())
+            .collect()
+    }
+
+    /// How many times each piece key appears, with unqualified keys counted under
+    /// `psi:`.
+    ///
+    /// ```
+    /// use psi_spell_encode::Spell;
+    ///
+    /// let spell = Spell::builder()
+    ///     .add_piece(0, 0, "psi:trick_add_motion")
+    ///     .add_piece(1, 0, "trick_add_motion")
+    ///     .add_piece(2, 0, "psi:selector_caster")
+    ///     .build();
+    /// let counts = spell.piece_key_counts();
+    /// assert_eq!(counts["psi:trick_add_motion"], 2);
+    /// assert_eq!(counts["psi:selector_caster"], 1);
+    /// ```
+    pub fn piece_key_counts(&self) -> BTreeMap<String, usize> {
+        let mut counts = BTreeMap::new();
+        for piece in &self.pieces {
+            let key = normalize_key(&piece.data.key).into_owned();
+            *counts.entry(key).or_insert(0) += 1;
         }
+        counts
+    }
 
-        #[inline]
-        fn read_until_nul<T>(cursor: &mut Cursor<T>) -> Vec<u8>
-        where
-            T: std::convert::AsRef<[u8]>,
-        {
-            read_until(cursor, 0)
+    /// The distinct piece keys in first-seen order, and for each piece the index of its
+    /// key in that table. Keys are compared exactly as stored.
+    ///
+    /// ```
+    /// use psi_spell_encode::Spell;
+    ///
+    /// let spell = Spell::builder()
+    ///     .add_piece(0, 0, "psi:selector_caster")
+    ///     .add_piece(1, 0, "psi:trick_blink")
+    ///     .add_piece(2, 0, "psi:selector_caster")
+    ///     .build();
+    /// let (keys, indices) = spell.key_index();
+    /// assert_eq!(keys, ["psi:selector_caster", "psi:trick_blink"]);
+    /// assert_eq!(indices, [0, 1, 0]);
+    /// ```
+    pub fn key_index(&self) -> (Vec<String>, Vec<usize>) {
+        let mut table: IndexMap<&str, ()> = IndexMap::new();
+        let indices = self
+            .pieces
+            .iter()
+            .map(|piece| table.insert_full(&piece.data.key, ()).0)
+            .collect();
+        let keys = table.into_keys().map(str::to_owned).collect();
+        (keys, indices)
+    }
+
+    /// Changes the key of every piece whose key is `from`, returning how many were
+    /// changed. Keys are compared with unqualified ones counted under `psi:`.
+    ///
+    /// ```
+    /// use psi_spell_encode::Spell;
+    ///
+    /// let mut spell = Spell::builder()
+    ///     .add_piece(0, 0, "psi:trick_old")
+    ///     .add_piece(1, 0, "trick_old")
+    ///     .add_piece(2, 0, "psi:selector_caster")
+    ///     .build();
+    /// assert_eq!(spell.replace_key("psi:trick_old", "psi:trick_new"), 2);
+    /// let keys: Vec<_> = spell.pieces.iter().map(|p| p.data.key.as_str()).collect();
+    /// assert_eq!(keys, ["psi:trick_new", "psi:trick_new", "psi:selector_caster"]);
+    /// ```
+    pub fn replace_key(&mut self, from: &str, to: &str) -> usize {
+        let from = normalize_key(from);
+        let mut replaced = 0;
+        for piece in &mut self.pieces {
+            if normalize_key(&piece.data.key) == from {
+                piece.data.key = to.to_owned();
+                replaced += 1;
+            }
         }
+        replaced
+    }
 
-        #[inline]
-        fn next<T>(cursor: &mut Cursor<T>) -> u8
-        where
-            T: std::convert::AsRef<[u8]>,
-        {
-            let mut a = [0];
-            cursor.read_exact(&mut a).unwrap();
-            a[0]
+    /// A rough size metric for ranking spells: the number of pieces.
+    #[inline]
+    pub fn complexity(&self) -> usize {
+        self.pieces.len()
+    }
+
+    /// Total number of params across all pieces.
+    ///
+    /// ```
+    /// use psi_spell_encode::{Side, Spell};
+    ///
+    /// let spell = Spell::builder()
+    ///     .add_piece(0, 0, "psi:operator_vector_sum")
+    ///     .param("_vector1", Side::Left)
+    ///     .param("_vector2", Side::Top)
+    ///     .add_piece(1, 0, "psi:trick_add_motion")
+    ///     .param("_target", Side::Left)
+    ///     .param("_direction", Side::Top)
+    ///     .param("_speed", Side::Bottom)
+    ///     .build();
+    /// assert_eq!(spell.connection_count(), 5);
+    /// ```
+    pub fn connection_count(&self) -> usize {
+        self.pieces
+            .iter()
+            .map(|piece| piece.data.params.as_ref().map_or(0, |params| params.len()))
+            .sum()
+    }
+
+    /// Every param name used by the pieces, split into `(builtin, custom)` by
+    /// [`is_builtin_param`].
+    ///
+    /// ```
+    /// use psi_spell_encode::{Side, Spell};
+    ///
+    /// let spell = Spell::builder()
+    ///     .add_piece(0, 0, "psi:trick_blink")
+    ///     .param("_target", Side::Left)
+    ///     .param("_foo", Side::Top)
+    ///     .build();
+    /// let (builtin, custom) = spell.param_names();
+    /// assert!(builtin.contains("_target") && !builtin.contains("_foo"));
+    /// assert!(custom.contains("_foo") && !custom.contains("_target"));
+    /// ```
+    pub fn param_names(&self) -> (BTreeSet<String>, BTreeSet<String>) {
+        self.pieces
+            .iter()
+            .flat_map(|piece| piece.data.params.iter().flatten())
+            .map(|(name, _)| name.clone())
+            .partition(|name| is_builtin_param(name))
+    }
+
+    /// `(x, y, comment)` for every piece with a non-empty comment, in reading order:
+    /// by row, then by column.
+    ///
+    /// ```
+    /// use psi_spell_encode::Spell;
+    ///
+    /// let spell = Spell::builder()
+    ///     .add_piece(2, 1, "psi:trick_blink")
+    ///     .comment("blink")
+    ///     .add_piece(0, 1, "psi:operator_entity_look")
+    ///     .add_piece(1, 0, "psi:selector_caster")
+    ///     .comment("me")
+    ///     .build();
+    /// assert_eq!(spell.comments(), [(1, 0, "me"), (2, 1, "blink")]);
+    /// ```
+    pub fn comments(&self) -> Vec<(u8, u8, &str)> {
+        let mut comments: Vec<_> = self
+            .pieces
+            .iter()
+            .filter_map(|piece| {
+                let comment = piece.data.comment.as_deref()?;
+                (!comment.is_empty()).then_some((piece.x, piece.y, comment))
+            })
+            .collect();
+        comments.sort_by_key(|&(x, y, _)| (y, x));
+        comments
+    }
+
+    /// Namespaces used by pieces that have no entry in [`Spell::mods`]. Psi itself is
+    /// always present, so `psi` is never reported.
+    pub fn missing_mod_declarations(&self) -> Vec<String> {
+        self.namespaces()
+            .into_iter()
+            .filter(|ns| ns != "psi" && !self.mods.iter().any(|m| m.name == *ns))
+            .collect()
+    }
+
+    /// Pieces bucketed by row `y`, each row sorted by `x`. Rows without pieces are
+    /// absent, and pieces sharing a cell are all kept in their original order.
+    pub fn rows(&self) -> BTreeMap<u8, Vec<&Piece>> {
+        let mut rows: BTreeMap<u8, Vec<&Piece>> = BTreeMap::new();
+        for piece in &self.pieces {
+            rows.entry(piece.y).or_default().push(piece);
+        }
+        for row in rows.values_mut() {
+            row.sort_by_key(|piece| piece.x);
         }
+        rows
+    }
+
+    /// The piece at `(x, y)`. If several pieces share the cell, the first one in
+    /// [`Spell::pieces`] is returned.
+    ///
+    /// ```
+    /// use psi_spell_encode::Spell;
+    ///
+    /// let spell = Spell::builder()
+    ///     .add_piece(0, 0, "psi:selector_caster")
+    ///     .add_piece(2, 1, "psi:trick_blink")
+    ///     .build();
+    /// assert_eq!(spell.piece_at(2, 1).unwrap().data.key, "psi:trick_blink");
+    /// ```
+    #[inline]
+    pub fn piece_at(&self, x: u8, y: u8) -> Option<&Piece> {
+        self.pieces
+            .iter()
+            .find(|piece| piece.x == x && piece.y == y)
+    }
+
+    /// Mutable counterpart to [`Spell::piece_at`].
+    #[inline]
+    pub fn piece_at_mut(&mut self, x: u8, y: u8) -> Option<&mut Piece> {
+        self.pieces
+            .iter_mut()
+            .find(|piece| piece.x == x && piece.y == y)
+    }
 
-        #[inline]
-        fn btos(b: Vec<u8>) -> String {
-            String::from_utf8(b).unwrap()
+    /// Pieces whose key matches `pred`, in spell order.
+    ///
+    /// ```
+    /// use psi_spell_encode::Spell;
+    ///
+    /// let spell = Spell::builder()
+    ///     .add_piece(0, 0, "psi:selector_caster")
+    ///     .add_piece(1, 0, "psi:trick_add_motion")
+    ///     .add_piece(2, 0, "psi:trick_blink")
+    ///     .build();
+    /// let tricks: Vec<_> = spell
+    ///     .find_pieces(|key| key.starts_with("psi:trick_"))
+    ///     .into_iter()
+    ///     .map(|piece| (piece.x, piece.y))
+    ///     .collect();
+    /// assert_eq!(tricks, [(1, 0), (2, 0)]);
+    /// ```
+    pub fn find_pieces<F: Fn(&str) -> bool>(&self, pred: F) -> Vec<&Piece> {
+        self.pieces
+            .iter()
+            .filter(|piece| pred(&piece.data.key))
+            .collect()
+    }
+
+    /// Mutable counterpart to [`Spell::find_pieces`].
+    pub fn find_pieces_mut<F: Fn(&str) -> bool>(&mut self, pred: F) -> Vec<&mut Piece> {
+        self.pieces
+            .iter_mut()
+            .filter(|piece| pred(&piece.data.key))
+            .collect()
+    }
+
+    /// Pieces keyed by `(x, y)`, for repeated lookups. Like [`Spell::piece_at`], only
+    /// the first piece in a shared cell is kept.
+    pub fn grid(&self) -> HashMap<(u8, u8), &Piece> {
+        let mut grid = HashMap::with_capacity(self.pieces.len());
+        for piece in &self.pieces {
+            grid.entry((piece.x, piece.y)).or_insert(piece);
         }
+        grid
+    }
 
-        let mut cursor = Cursor::new(data);
-        let name = btos(read_until_nul(&mut cursor));
-        let mut mods = Vec::new();
-        let mut pieces = Vec::new();
+    /// Shifts every piece so the smallest occupied `x` and `y` become 0, keeping the
+    /// pieces' relative layout.
+    ///
+    /// ```
+    /// use psi_spell_encode::Spell;
+    ///
+    /// let mut spell = Spell::builder().add_piece(2, 3, "psi:trick_blink").build();
+    /// spell.normalize_origin();
+    /// assert_eq!((spell.pieces[0].x, spell.pieces[0].y), (0, 0));
+    /// ```
+    pub fn normalize_origin(&mut self) {
+        let Some((min_x, min_y, _, _)) = self.bounds() else {
+            return;
+        };
+        for piece in &mut self.pieces {
+            piece.x -= min_x;
+            piece.y -= min_y;
+        }
+    }
 
-        {
-            let m = read_until(&mut cursor, b']');
-            for m in m.split(|b| *b == b';') {
-                let mut name = Vec::new();
-                let mut version = Vec::new();
-                let mut name_done = false;
-                for b in m {
-                    let b = *b;
-                    if b == b',' || b == b';' {
-                        name_done = true;
-                        continue;
-                    }
-                    if !name_done {
-                        name.push(b);
-                    } else {
-                        version.push(b);
-                    }
-                }
-                mods.push(Mod {
-                    name: btos(name),
-                    version: btos(version),
-                })
+    /// Copies `other`'s pieces into this spell, shifted right by `x_offset`, and adds
+    /// the mods of `other` that this spell does not list yet.
+    ///
+    /// Fails without changing anything if a shifted piece lands on a cell that is
+    /// already occupied, including by another piece of `other`, or if it would be shifted past `x` 255, which is reported as
+    /// [`SpellError::OutOfGrid`] at its position in `other`.
+    ///
+    /// ```
+    /// use psi_spell_encode::Spell;
+    ///
+    /// let mut spell = Spell::builder()
+    ///     .add_mod("psi", "2.0.0")
+    ///     .add_piece(0, 0, "psi:selector_caster")
+    ///     .build();
+    /// let other = Spell::builder()
+    ///     .add_mod("psi", "2.0.0")
+    ///     .add_mod("phi", "1.0")
+    ///     .add_piece(0, 0, "phi:trick_unknown")
+    ///     .build();
+    ///
+    /// spell.append_horizontal(&other, 1).unwrap();
+    /// let cells: Vec<_> = spell.pieces.iter().map(|p| (p.x, p.y)).collect();
+    /// assert_eq!(cells, [(0, 0), (1, 0)]);
+    /// ```
+    pub fn append_horizontal(&mut self, other: &Spell, x_offset: u8) -> Result<(), SpellError> {
+        let mut occupied: BTreeSet<_> = self.pieces.iter().map(|p| (p.x, p.y)).collect();
+        let mut pieces = Vec::with_capacity(other.pieces.len());
+        for piece in &other.pieces {
+            let (x, y) = (piece.x, piece.y);
+            let x = x
+                .checked_add(x_offset)
+                .ok_or(SpellError::OutOfGrid { x, y })?;
+            if !occupied.insert((x, y)) {
+                return Err(SpellError::OverlappingPieces { x, y });
             }
+            let mut piece = piece.clone();
+            piece.x = x;
+            pieces.push(piece);
         }
 
-        while cursor.fill_buf().map(|b| !b.is_empty()).unwrap() {
-            let xy = next(&mut cursor);
-            let x = xy >> 4;
-            let y = xy & 0b1111;
-            let mut key = read_until_nul(&mut cursor);
-            if !key.contains(&b':') {
-                key.reserve(4);
-                unsafe {
-                    std::ptr::copy(key.as_ptr(), key.as_mut_ptr().add(4), key.len());
-                    key.set_len(key.len() + 4);
-                }
-                key[0] = b'p';
-                key[1] = b's';
-                key[2] = b'i';
-                key[3] = b':';
+        self.pieces.extend(pieces);
+        for m in &other.mods {
+            if !self.requires_mod(&m.name) {
+                self.mods.push(m.clone());
             }
-            let key = btos(key);
+        }
+        Ok(())
+    }
 
-            let comment = btos(read_until_nul(&mut cursor));
-            let comment = if comment.is_empty() {
-                None
-            } else {
-                Some(comment)
-            };
+    /// Splits the pieces into the quadrants of vanilla Psi's 9x9 grid, in the order top
+    /// left, top right, bottom left, bottom right. The middle row and column count as
+    /// top and left. Each part keeps the mods, metadata and absolute coordinates, and
+    /// has its quadrant appended to the name.
+    ///
+    /// ```
+    /// use psi_spell_encode::Spell;
+    ///
+    /// let spell = Spell::builder()
+    ///     .name("Blink")
+    ///     .add_piece(8, 0, "psi:trick_blink")
+    ///     .build();
+    /// let [_, top_right, _, _] = spell.split_quadrants();
+    /// assert_eq!(top_right.name, "Blink (top right)");
+    /// assert_eq!(top_right.pieces, spell.pieces);
+    /// ```
+    pub fn split_quadrants(&self) -> [Spell; 4] {
+        const NAMES: [&str; 4] = ["top left", "top right", "bottom left", "bottom right"];
+        let grid = GridSpec::VANILLA;
+        let mut parts = NAMES.map(|quadrant| Spell {
+            mods: self.mods.clone(),
+            pieces: Vec::new(),
+            name: format!("{} ({quadrant})", self.name),
+            meta: self.meta.clone(),
+        });
+        for piece in &self.pieces {
+            let right = usize::from(piece.x as usize * 2 >= grid.width as usize);
+            let bottom = usize::from(piece.y as usize * 2 >= grid.height as usize);
+            parts[bottom * 2 + right].pieces.push(piece.clone());
+        }
+        parts
+    }
 
-            let mut params = HashMap::new();
-            let mut constant = None;
-
-            let ty = next(&mut cursor);
-            if ty == 255 {
-                constant = Some(btos(read_until_nul(&mut cursor)));
-            } else if ty != 254 {
-                let len = ty;
-                for _ in 0..len {
-                    let type_or_pos = next(&mut cursor);
-                    let param_key = if type_or_pos == 255 {
-                        btos(read_until_nul(&mut cursor))
-                    } else {
-                        BUILTIN_PARAMS[type_or_pos as usize].to_string()
-                    };
-
-                    let side = next(&mut cursor);
-                    params.insert(param_key, side);
-                }
+    /// The occupied area as `(min_x, min_y, max_x, max_y)`, inclusive, or `None` if the
+    /// spell has no pieces.
+    ///
+    /// ```
+    /// use psi_spell_encode::Spell;
+    ///
+    /// let spell = Spell::builder()
+    ///     .add_piece(2, 3, "psi:selector_caster")
+    ///     .add_piece(5, 1, "psi:operator_entity_look")
+    ///     .add_piece(4, 6, "psi:trick_blink")
+    ///     .build();
+    /// assert_eq!(spell.bounds(), Some((2, 1, 5, 6)));
+    /// ```
+    pub fn bounds(&self) -> Option<(u8, u8, u8, u8)> {
+        let (first, rest) = self.pieces.split_first()?;
+        Some(rest.iter().fold(
+            (first.x, first.y, first.x, first.y),
+            |(min_x, min_y, max_x, max_y), piece| {
+                (
+                    min_x.min(piece.x),
+                    min_y.min(piece.y),
+                    max_x.max(piece.x),
+                    max_y.max(piece.y),
+                )
+            },
+        ))
+    }
+
+    /// Orders [`Spell::pieces`] row by row, by `(y, x)`, so spells with the same layout
+    /// list their pieces in the same order. Pieces sharing a cell keep their relative
+    /// order.
+    ///
+    /// ```
+    /// use psi_spell_encode::Spell;
+    ///
+    /// let mut spell = Spell::builder()
+    ///     .add_piece(2, 1, "psi:trick_blink")
+    ///     .add_piece(0, 1, "psi:operator_entity_look")
+    ///     .add_piece(1, 0, "psi:selector_caster")
+    ///     .build();
+    /// spell.sort_pieces();
+    /// let cells: Vec<_> = spell.pieces.iter().map(|p| (p.x, p.y)).collect();
+    /// assert_eq!(cells, [(1, 0), (0, 1), (2, 1)]);
+    /// ```
+    #[inline]
+    pub fn sort_pieces(&mut self) {
+        self.pieces.sort_by_key(|piece| (piece.y, piece.x));
+    }
+
+    /// Removes pieces that are exact copies, position and data, of an earlier piece, and
+    /// returns how many were removed. Different pieces sharing a cell are kept, for
+    /// [`Spell::validate`] to report.
+    ///
+    /// ```
+    /// use psi_spell_encode::Spell;
+    ///
+    /// let mut spell = Spell::builder()
+    ///     .add_piece(1, 1, "psi:trick_blink")
+    ///     .add_piece(1, 1, "psi:trick_blink")
+    ///     .add_piece(2, 1, "psi:selector_caster")
+    ///     .build();
+    /// assert_eq!(spell.dedup_pieces(), 1);
+    /// assert_eq!(spell.pieces.len(), 2);
+    /// assert!(spell.validate().is_ok());
+    /// ```
+    pub fn dedup_pieces(&mut self) -> usize {
+        let before = self.pieces.len();
+        // Only pieces in the same cell can be copies, so each is compared against the
+        // kept pieces of its own cell.
+        let mut kept: HashMap<(u8, u8), Vec<usize>> = HashMap::new();
+        let mut duplicate = Vec::with_capacity(before);
+        for (i, piece) in self.pieces.iter().enumerate() {
+            let cell = kept.entry((piece.x, piece.y)).or_default();
+            let copy = cell.iter().any(|&j| self.pieces[j].data == piece.data);
+            if !copy {
+                cell.push(i);
             }
+            duplicate.push(copy);
+        }
+        let mut duplicate = duplicate.into_iter();
+        self.pieces.retain(|_| !duplicate.next().unwrap());
+        before - self.pieces.len()
+    }
 
-            let params = if params.is_empty() {
-                None
-            } else {
-                Some(params)
-            };
+    /// Shortens the name to at most [`Spell::MAX_NAME_LEN`] characters, never splitting
+    /// a character.
+    ///
+    /// ```
+    /// use psi_spell_encode::Spell;
+    ///
+    /// let mut spell = Spell::empty("A spell with a very long name");
+    /// spell.truncate_name();
+    /// assert_eq!(spell.name, "A spell with a very ");
+    /// ```
+    pub fn truncate_name(&mut self) {
+        if let Some((end, _)) = self.name.char_indices().nth(Self::MAX_NAME_LEN) {
+            self.name.truncate(end);
+        }
+    }
+
+    /// Removes every piece comment.
+    pub fn strip_comments(&mut self) {
+        for piece in &mut self.pieces {
+            piece.data.comment = None;
+        }
+    }
 
-            let data = SpellData {
-                key,
-                params,
-                constant,
-                comment,
+    /// A copy of the spell with every piece comment removed.
+    pub fn without_comments(&self) -> Spell {
+        let mut spell = self.clone();
+        spell.strip_comments();
+        spell
+    }
+
+    /// Blanks every mod version, so importers do not warn about version mismatches.
+    pub fn clear_mod_versions(&mut self) {
+        for m in &mut self.mods {
+            m.version.clear();
+        }
+    }
+
+    /// Whether `name` is listed in [`Spell::mods`].
+    #[inline]
+    pub fn requires_mod(&self, name: &str) -> bool {
+        self.mods.iter().any(|m| m.name == name)
+    }
+
+    /// Lists Psi itself first in [`Spell::mods`] with `version`, unless it is already
+    /// listed. Some importers warn when it is missing.
+    ///
+    /// ```
+    /// use psi_spell_encode::Spell;
+    ///
+    /// let mut spell = Spell::builder().add_mod("phi", "1.0").build();
+    /// spell.ensure_psi_mod("2.0.0");
+    /// assert!(spell.requires_mod("psi"));
+    /// ```
+    pub fn ensure_psi_mod(&mut self, version: &str) {
+        if !self.requires_mod("psi") {
+            let psi = Mod {
+                name: "psi".to_owned(),
+                version: version.to_owned(),
             };
+            self.mods.insert(0, psi);
+        }
+    }
 
-            let piece = Piece { data, x, y };
-            pieces.push(piece);
+    /// Sets the version of every mod named `mod_name`. Mods that are not listed are
+    /// not added.
+    pub fn set_mod_version(&mut self, mod_name: &str, version: &str) {
+        for m in self.mods.iter_mut().filter(|m| m.name == mod_name) {
+            m.version = version.to_owned();
         }
+    }
 
-        Self { name, mods, pieces }
+    /// Like [`Spell::bin`], but fails instead of silently corrupting spells that do not pass
+    /// [`Spell::validate`]. Pieces outside the 9x9 grid are still encoded.
+    #[inline]
+    pub fn try_bin(&self) -> Result<Vec<u8>, SpellError> {
+        self.check_encodable()?;
+        Ok(self.bin())
+    }
+
+    /// An encoding that is identical for spells differing only in the order of their
+    /// pieces, mods or params, in whether keys spell out `psi:`, in whitespace around
+    /// comments, in empty versus absent params, or in their [`Spell::meta`]. Pieces
+    /// sharing a cell keep their relative order.
+    pub fn canonical_bytes(&self) -> Vec<u8> {
+        let mut spell = self.clone();
+        spell
+            .mods
+            .sort_by(|a, b| (&a.name, &a.version).cmp(&(&b.name, &b.version)));
+        for piece in &mut spell.pieces {
+            let data = &mut piece.data;
+            data.comment = data
+                .comment
+                .as_deref()
+                .map(str::trim)
+                .filter(|comment| !comment.is_empty())
+                .map(str::to_owned);
+            if matches!(&data.params, Some(params) if params.is_empty()) {
+                data.params = None;
+            }
+            if let Some(params) = &mut data.params {
+                // Builtins first in table order, then custom params by name.
+                params.sort_by(|a, _, b, _| {
+                    let a = (builtin_param_index(a).is_none(), builtin_param_index(a), a);
+                    let b = (builtin_param_index(b).is_none(), builtin_param_index(b), b);
+                    a.cmp(&b)
+                });
+            }
+        }
+        spell.pieces.sort_by_key(|piece| (piece.y, piece.x));
+        spell.meta = None;
+        spell.bin()
+    }
+
+    /// SHA-256 of [`Spell::canonical_bytes`].
+    #[cfg(feature = "hash")]
+    pub fn content_hash(&self) -> [u8; 32] {
+        use sha2::{Digest, Sha256};
+
+        Sha256::digest(self.canonical_bytes()).into()
+    }
+}
+
+impl fmt::Display for Spell {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let pieces = self.pieces.len();
+        let mods = self.mods.len();
+        write!(
+            f,
+            "{} ({pieces} piece{}, {mods} mod{})",
+            self.name,
+            if pieces == 1 { "" } else { "s" },
+            if mods == 1 { "" } else { "s" },
+        )
+    }
+}
+
+/// Parses a share string, as [`decode_spell`] does.
+///
+/// ```
+/// use psi_spell_encode::Spell;
+///
+/// let spell = Spell::builder()
+///     .name("Blink")
+///     .add_piece(0, 0, "psi:trick_blink")
+///     .build();
+/// let share = spell.to_share_string().unwrap();
+/// assert_eq!(share.parse::<Spell>().unwrap(), spell);
+/// ```
+impl FromStr for Spell {
+    type Err = SpellParseError;
+
+    #[inline]
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        decode_spell(s)
     }
 }
 
@@ -306,78 +1363,665 @@ impl From<&Spell> for Vec<u8> {
     }
 }
 
-impl<T: Deref<Target = [u8]>> From<T> for Spell {
+impl TryFrom<&[u8]> for Spell {
+    type Error = DecodeError;
+
+    #[inline]
+    fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
+        Self::decode(value)
+    }
+}
+
+impl TryFrom<Vec<u8>> for Spell {
+    type Error = DecodeError;
+
     #[inline]
-    fn from(value: T) -> Self {
+    fn try_from(value: Vec<u8>) -> Result<Self, Self::Error> {
         Self::decode(&value)
     }
 }
 
-#[napi]
-pub fn spell_from_snbt(snbt: String) -> Result<Spell, napi::Error> {
-    let snbt =
-        quartz_nbt::snbt::parse(&snbt).map_err(|e| napi::Error::new(Status::GenericFailure, e))?;
+#[derive(Debug)]
+pub enum CodecError {
+    /// The input was not valid base64 for the expected alphabet.
+    Base64(base64_simd::Error),
+    /// The compressed stream could not be inflated.
+    Compression(io::Error),
+    /// A gzip level outside `0..=9` was requested.
+    InvalidLevel(u32),
+    /// The payload did not start with the expected codec tag.
+    UnknownCodec(u8),
+    /// The data inflated to more than the given number of bytes.
+    TooLarge(usize),
+}
+
+impl fmt::Display for CodecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Base64(e) => write!(f, "invalid base64: {e}"),
+            Self::Compression(e) => write!(f, "invalid compressed data: {e}"),
+            Self::InvalidLevel(level) => {
+                write!(f, "compression level must be between 0 and 9, got {level}")
+            }
+            Self::UnknownCodec(tag) => write!(f, "unrecognized codec tag {tag:#04x}"),
+            Self::TooLarge(max) => write!(f, "data inflates to more than {max} bytes"),
+        }
+    }
+}
+
+impl std::error::Error for CodecError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Base64(e) => Some(e),
+            Self::Compression(e) => Some(e),
+            Self::InvalidLevel(_) | Self::UnknownCodec(_) | Self::TooLarge(_) => None,
+        }
+    }
+}
+
+impl From<base64_simd::Error> for CodecError {
+    #[inline]
+    fn from(value: base64_simd::Error) -> Self {
+        Self::Base64(value)
+    }
+}
+
+#[derive(Debug)]
+pub enum NbtError {
+    Snbt(quartz_nbt::snbt::SnbtError),
+    Io(quartz_nbt::io::NbtIoError),
+    /// The top-level compound has no tag with this name.
+    MissingField(&'static str),
+    /// The tag `field` is present but is not a `expected`.
+    WrongType {
+        field: &'static str,
+        expected: &'static str,
+    },
+}
+
+impl fmt::Display for NbtError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Snbt(e) => write!(f, "{e}"),
+            Self::Io(e) => write!(f, "{e}"),
+            Self::MissingField(field) => {
+                write!(f, "not a spell: the {field} tag is missing")
+            }
+            Self::WrongType { field, expected } => {
+                write!(f, "not a spell: the {field} tag must be a {expected}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for NbtError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Snbt(e) => Some(e),
+            Self::Io(e) => Some(e),
+            Self::MissingField(_) | Self::WrongType { .. } => None,
+        }
+    }
+}
+
+impl From<quartz_nbt::snbt::SnbtError> for NbtError {
+    #[inline]
+    fn from(value: quartz_nbt::snbt::SnbtError) -> Self {
+        Self::Snbt(value)
+    }
+}
+
+impl From<quartz_nbt::io::NbtIoError> for NbtError {
+    #[inline]
+    fn from(value: quartz_nbt::io::NbtIoError) -> Self {
+        Self::Io(value)
+    }
+}
+
+/// Error from turning a shared spell string back into a [`Spell`].
+#[derive(Debug)]
+pub enum SpellParseError {
+    Codec(CodecError),
+    Decode(DecodeError),
+    Nbt(NbtError),
+}
+
+impl fmt::Display for SpellParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Codec(e) => write!(f, "{e}"),
+            Self::Decode(e) => write!(f, "{e}"),
+            Self::Nbt(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl std::error::Error for SpellParseError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Codec(e) => Some(e),
+            Self::Decode(e) => Some(e),
+            Self::Nbt(e) => Some(e),
+        }
+    }
+}
+
+impl From<CodecError> for SpellParseError {
+    #[inline]
+    fn from(value: CodecError) -> Self {
+        Self::Codec(value)
+    }
+}
+
+impl From<DecodeError> for SpellParseError {
+    #[inline]
+    fn from(value: DecodeError) -> Self {
+        Self::Decode(value)
+    }
+}
+
+impl From<NbtError> for SpellParseError {
+    #[inline]
+    fn from(value: NbtError) -> Self {
+        Self::Nbt(value)
+    }
+}
+
+/// Reads a spell from SNBT.
+///
+/// The top-level compound is checked for the `spellName`, `spellList` and
+/// `modsRequired` tags first, so pasting some other NBT reports which one is wrong.
+/// Their snake_case spellings are accepted too.
+///
+/// ```
+/// use psi_spell_encode::{spell_from_snbt, NbtError};
+///
+/// let err = spell_from_snbt("{spellList: [], modsRequired: []}").unwrap_err();
+/// assert!(matches!(err, NbtError::MissingField("spellName")));
+/// ```
+pub fn spell_from_snbt(snbt: &str) -> Result<Spell, NbtError> {
+    spell_from_compound(&quartz_nbt::snbt::parse(snbt)?)
+}
+
+fn spell_from_compound(root: &NbtCompound) -> Result<Spell, NbtError> {
+    check_spell_shape(root)?;
 
     let mut bytes = Vec::new();
-    if let Err(e) = quartz_nbt::io::write_nbt(&mut bytes, None, &snbt, Flavor::Uncompressed) {
-        return Err(napi::Error::new(Status::GenericFailure, e));
+    quartz_nbt::io::write_nbt(&mut bytes, None, root, Flavor::Uncompressed)?;
+
+    Ok(deserialize_from_buffer(&bytes)?.0)
+}
+
+/// The spell as an NBT tag tree, for tools that need to inspect or add tags that
+/// [`Spell`] has no field for.
+pub fn spell_to_nbt(spell: &Spell) -> Result<NbtTag, NbtError> {
+    Ok(NbtTag::Compound(spell_to_compound(spell)?))
+}
+
+/// Reads a spell from an NBT tag tree, ignoring tags that [`Spell`] has no field for.
+///
+/// ```
+/// use psi_spell_encode::{spell_from_nbt, spell_to_nbt, Spell};
+///
+/// let spell = Spell::builder()
+///     .name("Blink")
+///     .add_piece(0, 0, "psi:selector_caster")
+///     .build();
+/// let nbt = spell_to_nbt(&spell).unwrap();
+/// assert_eq!(spell_from_nbt(&nbt).unwrap(), spell);
+/// ```
+pub fn spell_from_nbt(nbt: &NbtTag) -> Result<Spell, NbtError> {
+    match nbt {
+        NbtTag::Compound(root) => spell_from_compound(root),
+        _ => Err(NbtError::WrongType {
+            field: "root",
+            expected: "compound",
+        }),
     }
+}
 
-    Ok(deserialize_from_buffer(&bytes)
-        .map_err(|e| napi::Error::new(Status::GenericFailure, e))?
-        .0)
+fn check_spell_shape(root: &NbtCompound) -> Result<(), NbtError> {
+    let fields: [(&'static str, &'static str, &'static str); 3] = [
+        ("spellName", "spell_name", "string"),
+        ("spellList", "spell_list", "list"),
+        ("modsRequired", "mods_required", "list"),
+    ];
+    for (field, alias, expected) in fields {
+        let tag = root.inner().get(field).or_else(|| root.inner().get(alias));
+        let ok = match tag {
+            None => return Err(NbtError::MissingField(field)),
+            Some(NbtTag::String(_)) => expected == "string",
+            Some(NbtTag::List(_)) => expected == "list",
+            Some(_) => false,
+        };
+        if !ok {
+            return Err(NbtError::WrongType { field, expected });
+        }
+    }
+    Ok(())
 }
 
-#[napi]
-pub fn decode_spell_from_bytes(bytes: Vec<u8>) -> Spell {
-    bytes.into()
+fn spell_to_compound(spell: &Spell) -> Result<NbtCompound, NbtError> {
+    let ser = quartz_nbt::serde::serialize(spell, None, Flavor::Uncompressed)?;
+    Ok(quartz_nbt::io::read_nbt(&mut Cursor::new(ser), Flavor::Uncompressed)?.0)
 }
 
-#[napi]
-pub fn encode_bytes_to_url_safe(bytes: Vec<u8>) -> String {
-    const LEVEL: flate2::Compression = flate2::Compression::fast();
-    let mut gz = GzEncoder::new(bytes.as_slice(), LEVEL);
-    let mut encoded = Vec::new();
-    gz.read_to_end(&mut encoded).unwrap();
+/// Writes a spell as compact SNBT. Unknown piece data tags read by
+/// [`spell_from_snbt`] are kept in [`SpellData::extra`] and written back out.
+///
+/// ```
+/// use psi_spell_encode::{spell_from_snbt, spell_to_snbt};
+///
+/// let snbt = r#"{
+///     spellName: "x",
+///     modsRequired: [],
+///     spellList: [{x: 0b, y: 0b, data: {key: "psi:selector_caster", addonTag: "kept"}}]
+/// }"#;
+/// let spell = spell_from_snbt(snbt).unwrap();
+/// assert!(spell_to_snbt(&spell).unwrap().contains("addonTag"));
+/// ```
+pub fn spell_to_snbt(spell: &Spell) -> Result<String, NbtError> {
+    Ok(spell_to_compound(spell)?.to_snbt())
+}
 
-    base64_simd::URL_SAFE.encode_to_string(encoded)
+/// Like [`spell_to_snbt`], but indented over multiple lines with compound keys sorted,
+/// so the output is stable and can be diffed. The result parses with
+/// [`spell_from_snbt`].
+///
+/// ```
+/// use psi_spell_encode::{spell_from_snbt, spell_to_snbt_pretty, Spell};
+///
+/// let spell = Spell::builder()
+///     .name("Blink")
+///     .add_piece(0, 0, "psi:selector_caster")
+///     .build();
+/// let snbt = spell_to_snbt_pretty(&spell).unwrap();
+/// assert_eq!(spell_from_snbt(&snbt).unwrap(), spell);
+/// ```
+pub fn spell_to_snbt_pretty(spell: &Spell) -> Result<String, NbtError> {
+    let mut out = String::new();
+    write_pretty_snbt(&NbtTag::Compound(spell_to_compound(spell)?), 0, &mut out);
+    Ok(out)
+}
+
+fn write_pretty_snbt(tag: &NbtTag, depth: usize, out: &mut String) {
+    const INDENT: &str = "    ";
+
+    match tag {
+        NbtTag::Compound(compound) if !compound.inner().is_empty() => {
+            let mut entries: Vec<_> = compound.inner().iter().collect();
+            entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+            out.push_str("{\n");
+            for (i, (key, value)) in entries.iter().enumerate() {
+                out.push_str(&INDENT.repeat(depth + 1));
+                let bare = key
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.' | '+'));
+                if bare && !key.is_empty() {
+                    out.push_str(key);
+                } else {
+                    out.push_str(&NbtTag::String(key.to_string()).to_snbt());
+                }
+                out.push_str(": ");
+                write_pretty_snbt(value, depth + 1, out);
+                if i + 1 < entries.len() {
+                    out.push(',');
+                }
+                out.push('\n');
+            }
+            out.push_str(&INDENT.repeat(depth));
+            out.push('}');
+        }
+        NbtTag::List(list) if !list.inner().is_empty() => {
+            let items = list.inner();
+            out.push_str("[\n");
+            for (i, item) in items.iter().enumerate() {
+                out.push_str(&INDENT.repeat(depth + 1));
+                write_pretty_snbt(item, depth + 1, out);
+                if i + 1 < items.len() {
+                    out.push(',');
+                }
+                out.push('\n');
+            }
+            out.push_str(&INDENT.repeat(depth));
+            out.push(']');
+        }
+        _ => out.push_str(&tag.to_snbt()),
+    }
+}
+
+/// Reads a spell in Psi's own export format: base64 of the gzip-compressed binary NBT.
+pub fn spell_from_psi_export(export: &str) -> Result<Spell, SpellParseError> {
+    let bytes = base64_simd::STANDARD
+        .decode_to_vec(export.trim())
+        .map_err(CodecError::from)?;
+    let (spell, _) =
+        quartz_nbt::serde::deserialize(&bytes, Flavor::GzCompressed).map_err(NbtError::from)?;
+    Ok(spell)
+}
+
+/// Writes a spell in Psi's own export format, the inverse of [`spell_from_psi_export`].
+pub fn spell_to_psi_export(spell: &Spell) -> Result<String, NbtError> {
+    let bytes = quartz_nbt::serde::serialize(spell, None, Flavor::GzCompressed)?;
+    Ok(base64_simd::STANDARD.encode_to_string(bytes))
+}
+
+/// Converts a [`Spell::bin`] buffer straight to the NBT tag tree of
+/// [`spell_to_nbt`].
+///
+/// ```
+/// use psi_spell_encode::{bin_to_nbt, nbt_to_bin, Spell};
+///
+/// let bin = Spell::builder()
+///     .name("Blink")
+///     .add_piece(0, 0, "psi:trick_blink")
+///     .build()
+///     .bin();
+/// let nbt = bin_to_nbt(&bin).unwrap();
+/// assert_eq!(nbt_to_bin(&nbt).unwrap(), bin);
+/// ```
+pub fn bin_to_nbt(data: &[u8]) -> Result<NbtTag, SpellParseError> {
+    Ok(spell_to_nbt(&Spell::decode(data)?)?)
+}
+
+/// Converts an NBT tag tree to a [`Spell::bin`] buffer. Tags that [`Spell`] has no
+/// field for cannot be represented in the binary format and are dropped.
+pub fn nbt_to_bin(nbt: &NbtTag) -> Result<Vec<u8>, SpellParseError> {
+    let spell = spell_from_nbt(nbt)?;
+    Ok(spell.try_bin().map_err(DecodeError::from)?)
+}
+
+const DEFAULT_LEVEL: flate2::Compression = flate2::Compression::fast();
+
+#[inline]
+fn gzip(bytes: &[u8], level: flate2::Compression) -> Vec<u8> {
+    let mut gz = GzEncoder::new(Vec::new(), level);
+    gz.write_all(bytes).unwrap();
+    gz.finish().unwrap()
 }
 
-#[napi]
-pub fn decode_url_safe_to_bytes(url_safe: String) -> Result<Vec<u8>, napi::Error> {
-    let mut bytes = url_safe.into_bytes();
-    let decoded = base64_simd::URL_SAFE
-        .decode_inplace(&mut bytes)
-        .map_err(|e| napi::Error::new(Status::GenericFailure, e))?
-        .to_vec();
-    let mut gz = GzDecoder::new(&decoded[..]);
+#[inline]
+fn gunzip(bytes: &[u8]) -> Result<Vec<u8>, CodecError> {
+    let mut gz = GzDecoder::new(bytes);
     let mut decoded = Vec::new();
     gz.read_to_end(&mut decoded)
-        .map_err(|e| napi::Error::new(Status::GenericFailure, e))?;
+        .map_err(CodecError::Compression)?;
+    Ok(decoded)
+}
+
+pub fn encode_bytes_to_url_safe(bytes: &[u8]) -> String {
+    base64_simd::URL_SAFE.encode_to_string(gzip(bytes, DEFAULT_LEVEL))
+}
+
+/// Like [`encode_bytes_to_url_safe`], but gzips straight from `reader` instead of
+/// requiring the whole input up front.
+pub fn encode_reader_to_url_safe<R: Read>(mut reader: R) -> io::Result<String> {
+    let mut gz = GzEncoder::new(Vec::new(), DEFAULT_LEVEL);
+    io::copy(&mut reader, &mut gz)?;
+    Ok(base64_simd::URL_SAFE.encode_to_string(gz.finish()?))
+}
+
+/// Like [`encode_bytes_to_url_safe`], but with an explicit gzip level from 0 (none) to 9 (best).
+pub fn encode_bytes_to_url_safe_with_level(bytes: &[u8], level: u32) -> Result<String, CodecError> {
+    if level > 9 {
+        return Err(CodecError::InvalidLevel(level));
+    }
+    let level = flate2::Compression::new(level);
+    Ok(base64_simd::URL_SAFE.encode_to_string(gzip(bytes, level)))
+}
+
+/// Decodes a string from [`encode_bytes_to_url_safe`]. ASCII whitespace anywhere in
+/// `url_safe` and missing or extra trailing `=` padding are tolerated, since pasted
+/// strings often pick them up.
+///
+/// ```
+/// use psi_spell_encode::{decode_url_safe_to_bytes, encode_bytes_to_url_safe};
+///
+/// let encoded = encode_bytes_to_url_safe(b"spell");
+/// let pasted = format!("{encoded}\n");
+/// assert_eq!(decode_url_safe_to_bytes(&pasted).unwrap(), b"spell");
+/// ```
+pub fn decode_url_safe_to_bytes(url_safe: &str) -> Result<Vec<u8>, CodecError> {
+    gunzip(&decode_pasted_url_safe(url_safe)?)
+}
+
+/// Like [`decode_url_safe_to_bytes`], but fails with [`CodecError::TooLarge`] instead
+/// of inflating more than `max_bytes`, so untrusted input cannot exhaust memory.
+///
+/// ```
+/// use psi_spell_encode::{decode_url_safe_to_bytes_limited, encode_bytes_to_url_safe};
+///
+/// let encoded = encode_bytes_to_url_safe(b"spell");
+/// assert_eq!(decode_url_safe_to_bytes_limited(&encoded, 4096).unwrap(), b"spell");
+/// ```
+pub fn decode_url_safe_to_bytes_limited(
+    url_safe: &str,
+    max_bytes: usize,
+) -> Result<Vec<u8>, CodecError> {
+    let compressed = decode_pasted_url_safe(url_safe)?;
+    let mut decoded = Vec::new();
+    GzDecoder::new(&compressed[..])
+        .take((max_bytes as u64).saturating_add(1))
+        .read_to_end(&mut decoded)
+        .map_err(CodecError::Compression)?;
+    if decoded.len() > max_bytes {
+        return Err(CodecError::TooLarge(max_bytes));
+    }
+    Ok(decoded)
+}
+
+/// Base64-decodes a pasted URL-safe string, ignoring whitespace and padding.
+fn decode_pasted_url_safe(url_safe: &str) -> Result<Vec<u8>, CodecError> {
+    let mut cleaned: Vec<u8> = url_safe
+        .bytes()
+        .filter(|b| !b.is_ascii_whitespace())
+        .collect();
+    while cleaned.last() == Some(&b'=') {
+        cleaned.pop();
+    }
+    Ok(base64_simd::URL_SAFE_NO_PAD.decode_to_vec(cleaned)?)
+}
+
+/// Like [`decode_url_safe_to_bytes`], but rejects whitespace and requires the padding
+/// that [`encode_bytes_to_url_safe`] writes.
+pub fn decode_url_safe_to_bytes_strict(url_safe: &str) -> Result<Vec<u8>, CodecError> {
+    gunzip(&base64_simd::URL_SAFE.decode_to_vec(url_safe)?)
+}
+
+pub fn encode_bytes_to_base64(bytes: &[u8]) -> String {
+    base64_simd::STANDARD.encode_to_string(gzip(bytes, DEFAULT_LEVEL))
+}
+
+pub fn decode_base64_to_bytes(base64: &str) -> Result<Vec<u8>, CodecError> {
+    gunzip(&base64_simd::STANDARD.decode_to_vec(base64)?)
+}
+
+// Leading bytes marking payloads that are not plain gzip. Gzip output always starts
+// with `0x1f`, so tagged payloads can be told apart from it.
+const RAW_TAG: u8 = 0x00;
+#[cfg(feature = "zstd")]
+const ZSTD_TAG: u8 = 0x01;
+const DEFLATE_TAG: u8 = 0x02;
+
+#[cfg(feature = "zstd")]
+const ZSTD_LEVEL: i32 = 19;
+
+/// Like [`encode_bytes_to_url_safe`], but compresses with zstd instead of gzip.
+#[cfg(feature = "zstd")]
+pub fn encode_bytes_zstd(bytes: &[u8]) -> String {
+    let mut encoded = vec![ZSTD_TAG];
+    zstd::stream::copy_encode(bytes, &mut encoded, ZSTD_LEVEL).unwrap();
+    base64_simd::URL_SAFE.encode_to_string(encoded)
+}
+
+#[cfg(feature = "zstd")]
+pub fn decode_bytes_zstd(url_safe: &str) -> Result<Vec<u8>, CodecError> {
+    let decoded = base64_simd::URL_SAFE.decode_to_vec(url_safe)?;
+    match decoded.split_first() {
+        Some((&ZSTD_TAG, payload)) => {
+            zstd::stream::decode_all(payload).map_err(CodecError::Compression)
+        }
+        Some((&tag, _)) => Err(CodecError::UnknownCodec(tag)),
+        None => Err(CodecError::Compression(io::ErrorKind::UnexpectedEof.into())),
+    }
+}
+
+/// Like [`encode_bytes_to_url_safe`], but as a raw DEFLATE stream, without gzip's
+/// header and checksum footer.
+///
+/// ```
+/// use psi_spell_encode::{decode_url_safe_deflate_to_bytes, encode_bytes_to_url_safe_deflate};
+///
+/// let encoded = encode_bytes_to_url_safe_deflate(b"spell");
+/// assert_eq!(decode_url_safe_deflate_to_bytes(&encoded).unwrap(), b"spell");
+/// ```
+pub fn encode_bytes_to_url_safe_deflate(bytes: &[u8]) -> String {
+    let mut deflate = DeflateEncoder::new(vec![DEFLATE_TAG], DEFAULT_LEVEL);
+    deflate.write_all(bytes).unwrap();
+    base64_simd::URL_SAFE.encode_to_string(deflate.finish().unwrap())
+}
+
+pub fn decode_url_safe_deflate_to_bytes(url_safe: &str) -> Result<Vec<u8>, CodecError> {
+    let decoded = base64_simd::URL_SAFE.decode_to_vec(url_safe)?;
+    match decoded.split_first() {
+        Some((&DEFLATE_TAG, payload)) => inflate(payload),
+        Some((&tag, _)) => Err(CodecError::UnknownCodec(tag)),
+        None => Err(CodecError::Compression(io::ErrorKind::UnexpectedEof.into())),
+    }
+}
+
+#[inline]
+fn inflate(bytes: &[u8]) -> Result<Vec<u8>, CodecError> {
+    let mut decoded = Vec::new();
+    DeflateDecoder::new(bytes)
+        .read_to_end(&mut decoded)
+        .map_err(CodecError::Compression)?;
     Ok(decoded)
 }
 
-#[napi]
-pub fn encode_spell_to_bytes(spell: &Spell) -> Vec<u8> {
-    spell.into()
+/// Base64-encodes `bytes` without compressing them, for inspecting the binary format
+/// or for spells small enough that gzip would only make them longer.
+pub fn encode_bytes_to_base64_raw(bytes: &[u8]) -> String {
+    let mut tagged = Vec::with_capacity(1 + bytes.len());
+    tagged.push(RAW_TAG);
+    tagged.extend_from_slice(bytes);
+    base64_simd::STANDARD.encode_to_string(tagged)
+}
+
+pub fn decode_base64_raw_to_bytes(base64: &str) -> Result<Vec<u8>, CodecError> {
+    let mut decoded = base64_simd::STANDARD.decode_to_vec(base64)?;
+    match decoded.first() {
+        Some(&RAW_TAG) => {
+            decoded.remove(0);
+            Ok(decoded)
+        }
+        Some(&tag) => Err(CodecError::UnknownCodec(tag)),
+        None => Err(CodecError::Compression(io::ErrorKind::UnexpectedEof.into())),
+    }
+}
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+#[cfg(feature = "zstd")]
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+/// Decodes a string produced by any of the encoders in this crate, detecting the
+/// base64 alphabet and the compression codec from the data itself.
+pub fn decode_any_to_bytes(s: &str) -> Result<Vec<u8>, CodecError> {
+    let decoded = match base64_simd::URL_SAFE.decode_to_vec(s) {
+        Ok(decoded) => decoded,
+        Err(e) => base64_simd::STANDARD.decode_to_vec(s).map_err(|_| e)?,
+    };
+
+    if decoded.starts_with(&GZIP_MAGIC) {
+        return gunzip(&decoded);
+    }
+    #[cfg(feature = "zstd")]
+    if decoded.starts_with(&ZSTD_MAGIC) {
+        return zstd::stream::decode_all(&decoded[..]).map_err(CodecError::Compression);
+    }
+    match decoded.split_first() {
+        Some((&RAW_TAG, payload)) => Ok(payload.to_vec()),
+        Some((&DEFLATE_TAG, payload)) => inflate(payload),
+        #[cfg(feature = "zstd")]
+        Some((&ZSTD_TAG, payload)) => {
+            zstd::stream::decode_all(payload).map_err(CodecError::Compression)
+        }
+        Some((&tag, _)) => Err(CodecError::UnknownCodec(tag)),
+        None => Err(CodecError::Compression(io::ErrorKind::UnexpectedEof.into())),
+    }
+}
+
+pub fn decode_spell(url_safe: &str) -> Result<Spell, SpellParseError> {
+    Ok(Spell::decode(&decode_url_safe_to_bytes(url_safe)?)?)
+}
+
+/// Reads only the name of the spell in a share string, see [`Spell::peek_name`].
+pub fn peek_name_from_url(url_safe: &str) -> Result<String, SpellParseError> {
+    Ok(Spell::peek_name(&decode_url_safe_to_bytes(url_safe)?)?)
+}
+
+/// Encodes several spells into one share string. Compressing them together lets gzip
+/// reuse what the spells have in common.
+///
+/// ```
+/// use psi_spell_encode::{decode_spells, encode_spells, Spell};
+///
+/// let spells = [Spell::empty("One"), Spell::empty("Two")];
+/// let encoded = encode_spells(&spells).unwrap();
+/// assert_eq!(decode_spells(&encoded).unwrap(), spells);
+/// ```
+pub fn encode_spells(spells: &[Spell]) -> Result<String, SpellError> {
+    for spell in spells {
+        spell.check_encodable()?;
+    }
+    let mut gz = GzEncoder::new(Vec::new(), DEFAULT_LEVEL);
+    format::write_spells(spells, &mut gz).unwrap();
+    Ok(base64_simd::URL_SAFE.encode_to_string(gz.finish().unwrap()))
+}
+
+/// Decodes a string produced by [`encode_spells`], keeping the spells in order.
+pub fn decode_spells(url_safe: &str) -> Result<Vec<Spell>, SpellParseError> {
+    Ok(format::read_spells(&decode_url_safe_to_bytes(url_safe)?)?)
 }
 
-#[napi]
-pub fn decode_spell(url_safe: Utf16String) -> Result<Spell, napi::Error> {
-    Ok(Spell::decode(&decode_url_safe_to_bytes(
-        (*url_safe).to_string(),
-    )?))
+/// Sizes at each stage of [`encode_spell_with_stats`], in bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "napi", napi(object))]
+pub struct EncodeStats {
+    /// Length of the [`Spell::bin`] buffer.
+    pub raw_len: u32,
+    /// Length after gzip.
+    pub compressed_len: u32,
+    /// Length of the base64 share string.
+    pub final_len: u32,
 }
 
-#[napi]
-pub fn encode_spell(spell: &Spell) -> Result<Utf16String, napi::Error> {
-    Ok(encode_bytes_to_url_safe(encode_spell_to_bytes(spell)).into())
+/// Like [`encode_spell`], but also reports how large the spell was at each stage.
+///
+/// ```
+/// use psi_spell_encode::{decode_spell, encode_spell_with_stats, Spell};
+///
+/// let spell = Spell::builder().add_piece(0, 0, "psi:trick_blink").build();
+/// let (encoded, stats) = encode_spell_with_stats(&spell).unwrap();
+/// assert_eq!(stats.raw_len as usize, spell.bin().len());
+/// assert_eq!(stats.final_len as usize, encoded.len());
+/// assert_eq!(decode_spell(&encoded).unwrap(), spell);
+/// ```
+pub fn encode_spell_with_stats(spell: &Spell) -> Result<(String, EncodeStats), SpellError> {
+    let bin = spell.try_bin()?;
+    let compressed = gzip(&bin, DEFAULT_LEVEL);
+    let encoded = base64_simd::URL_SAFE.encode_to_string(&compressed);
+    let stats = EncodeStats {
+        raw_len: bin.len() as u32,
+        compressed_len: compressed.len() as u32,
+        final_len: encoded.len() as u32,
+    };
+    Ok((encoded, stats))
 }
 
-#[napi]
-pub fn spell_to_snbt(spell: &Spell) -> Result<String, napi::Error> {
-    let ser = quartz_nbt::serde::serialize(spell, None, Flavor::Uncompressed).unwrap();
-    quartz_nbt::io::read_nbt(&mut Cursor::new(ser), Flavor::Uncompressed)
-        .map(|o| o.0.to_snbt())
-        .map_err(|e| napi::Error::new(Status::GenericFailure, e))
+pub fn encode_spell(spell: &Spell) -> Result<String, SpellError> {
+    spell.check_encodable()?;
+    let mut gz = GzEncoder::new(Vec::new(), DEFAULT_LEVEL);
+    spell.write_bin(&mut gz).unwrap();
+    Ok(base64_simd::URL_SAFE.encode_to_string(gz.finish().unwrap()))
 }
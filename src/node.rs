@@ -0,0 +1,258 @@
+//! Node bindings, built with the `napi` feature.
+
+use napi::{
+    bindgen_prelude::{ClassInstance, Utf16String},
+    Status,
+};
+
+use crate::{
+    CodecError, DecodeError, EncodeStats, NbtError, Spell, SpellError, SpellParseError,
+    ValidationIssue,
+};
+
+impl From<DecodeError> for napi::Error {
+    #[inline]
+    fn from(value: DecodeError) -> Self {
+        napi::Error::new(Status::GenericFailure, value)
+    }
+}
+
+impl From<CodecError> for napi::Error {
+    #[inline]
+    fn from(value: CodecError) -> Self {
+        let status = match value {
+            CodecError::InvalidLevel(_) => Status::InvalidArg,
+            _ => Status::GenericFailure,
+        };
+        napi::Error::new(status, value)
+    }
+}
+
+impl From<SpellError> for napi::Error {
+    #[inline]
+    fn from(value: SpellError) -> Self {
+        napi::Error::new(Status::InvalidArg, value)
+    }
+}
+
+impl From<NbtError> for napi::Error {
+    #[inline]
+    fn from(value: NbtError) -> Self {
+        let status = match value {
+            NbtError::MissingField(_) | NbtError::WrongType { .. } => Status::InvalidArg,
+            _ => Status::GenericFailure,
+        };
+        napi::Error::new(status, value)
+    }
+}
+
+impl From<SpellParseError> for napi::Error {
+    #[inline]
+    fn from(value: SpellParseError) -> Self {
+        match value {
+            SpellParseError::Codec(e) => e.into(),
+            SpellParseError::Decode(e) => e.into(),
+            SpellParseError::Nbt(e) => e.into(),
+        }
+    }
+}
+
+#[napi]
+pub fn spell_from_snbt(snbt: String) -> Result<Spell, napi::Error> {
+    Ok(crate::spell_from_snbt(&snbt)?)
+}
+
+/// Reads a spell in Psi's own export format: base64 of the gzip-compressed binary NBT.
+#[napi]
+pub fn spell_from_psi_export(psi_export: String) -> Result<Spell, napi::Error> {
+    Ok(crate::spell_from_psi_export(&psi_export)?)
+}
+
+/// Writes a spell in Psi's own export format, the inverse of [`spell_from_psi_export`].
+#[napi]
+pub fn spell_to_psi_export(spell: &Spell) -> Result<String, napi::Error> {
+    Ok(crate::spell_to_psi_export(spell)?)
+}
+
+#[napi]
+pub fn decode_spell_from_bytes(bytes: Vec<u8>) -> Result<Spell, napi::Error> {
+    Ok(bytes.try_into()?)
+}
+
+#[napi]
+pub fn encode_bytes_to_url_safe(bytes: Vec<u8>) -> String {
+    crate::encode_bytes_to_url_safe(&bytes)
+}
+
+/// Like [`encode_bytes_to_url_safe`], but with an explicit gzip level from 0 (none) to 9 (best).
+#[napi]
+pub fn encode_bytes_to_url_safe_with_level(
+    bytes: Vec<u8>,
+    level: u32,
+) -> Result<String, napi::Error> {
+    Ok(crate::encode_bytes_to_url_safe_with_level(&bytes, level)?)
+}
+
+#[napi]
+pub fn decode_url_safe_to_bytes(url_safe: String) -> Result<Vec<u8>, napi::Error> {
+    Ok(crate::decode_url_safe_to_bytes(&url_safe)?)
+}
+
+/// Like [`decode_url_safe_to_bytes`], but fails instead of inflating more than
+/// `max_bytes`, so untrusted input cannot exhaust memory.
+#[napi]
+pub fn decode_url_safe_to_bytes_limited(
+    url_safe: String,
+    max_bytes: u32,
+) -> Result<Vec<u8>, napi::Error> {
+    Ok(crate::decode_url_safe_to_bytes_limited(
+        &url_safe,
+        max_bytes as usize,
+    )?)
+}
+
+#[napi]
+pub fn encode_bytes_to_base64(bytes: Vec<u8>) -> String {
+    crate::encode_bytes_to_base64(&bytes)
+}
+
+#[napi]
+pub fn decode_base64_to_bytes(base64: String) -> Result<Vec<u8>, napi::Error> {
+    Ok(crate::decode_base64_to_bytes(&base64)?)
+}
+
+/// Like [`encode_bytes_to_url_safe`], but as a raw DEFLATE stream, without gzip's
+/// header and checksum footer.
+#[napi]
+pub fn encode_bytes_to_url_safe_deflate(bytes: Vec<u8>) -> String {
+    crate::encode_bytes_to_url_safe_deflate(&bytes)
+}
+
+#[napi]
+pub fn decode_url_safe_deflate_to_bytes(url_safe: String) -> Result<Vec<u8>, napi::Error> {
+    Ok(crate::decode_url_safe_deflate_to_bytes(&url_safe)?)
+}
+
+/// Base64-encodes `bytes` without compressing them, for inspecting the binary format
+/// or for spells small enough that gzip would only make them longer.
+#[napi]
+pub fn encode_bytes_to_base64_raw(bytes: Vec<u8>) -> String {
+    crate::encode_bytes_to_base64_raw(&bytes)
+}
+
+#[napi]
+pub fn decode_base64_raw_to_bytes(base64: String) -> Result<Vec<u8>, napi::Error> {
+    Ok(crate::decode_base64_raw_to_bytes(&base64)?)
+}
+
+#[cfg(feature = "zstd")]
+#[napi]
+pub fn encode_bytes_zstd(bytes: Vec<u8>) -> String {
+    crate::encode_bytes_zstd(&bytes)
+}
+
+#[cfg(feature = "zstd")]
+#[napi]
+pub fn decode_bytes_zstd(url_safe: String) -> Result<Vec<u8>, napi::Error> {
+    Ok(crate::decode_bytes_zstd(&url_safe)?)
+}
+
+/// Decodes a string produced by any of the encoders in this crate, detecting the
+/// base64 alphabet and the compression codec from the data itself.
+#[napi]
+pub fn decode_any_to_bytes(s: String) -> Result<Vec<u8>, napi::Error> {
+    Ok(crate::decode_any_to_bytes(&s)?)
+}
+
+#[napi]
+pub fn encode_spell_to_bytes(spell: &Spell) -> Result<Vec<u8>, napi::Error> {
+    Ok(spell.try_bin()?)
+}
+
+#[napi]
+pub fn decode_spell(url_safe: Utf16String) -> Result<Spell, napi::Error> {
+    Ok(crate::decode_spell(&url_safe)?)
+}
+
+/// Reads only the name of the spell in a share string, without decoding its pieces.
+#[napi]
+pub fn peek_name_from_url(url_safe: String) -> Result<String, napi::Error> {
+    Ok(crate::peek_name_from_url(&url_safe)?)
+}
+
+/// Encodes several spells into one share string. Compressing them together lets gzip
+/// reuse what the spells have in common.
+#[napi]
+pub fn encode_spells(spells: Vec<ClassInstance<Spell>>) -> Result<String, napi::Error> {
+    let spells: Vec<Spell> = spells.iter().map(|spell| Spell::clone(spell)).collect();
+    Ok(crate::encode_spells(&spells)?)
+}
+
+/// Decodes a string produced by [`encode_spells`], keeping the spells in order.
+#[napi]
+pub fn decode_spells(url_safe: String) -> Result<Vec<Spell>, napi::Error> {
+    Ok(crate::decode_spells(&url_safe)?)
+}
+
+/// A share string together with its [`EncodeStats`].
+#[napi(object)]
+pub struct EncodedSpell {
+    pub encoded: String,
+    pub stats: EncodeStats,
+}
+
+/// Like [`encode_spell`], but also reports how large the spell was at each stage.
+#[napi]
+pub fn encode_spell_with_stats(spell: &Spell) -> Result<EncodedSpell, napi::Error> {
+    let (encoded, stats) = crate::encode_spell_with_stats(spell)?;
+    Ok(EncodedSpell { encoded, stats })
+}
+
+#[napi]
+pub fn encode_spell(spell: &Spell) -> Result<Utf16String, napi::Error> {
+    Ok(crate::encode_spell(spell)?.into())
+}
+
+#[napi]
+pub fn spell_to_snbt(spell: &Spell) -> Result<String, napi::Error> {
+    Ok(crate::spell_to_snbt(spell)?)
+}
+
+/// Like `spellToSnbt`, but indented over multiple lines with compound keys sorted.
+#[napi]
+pub fn spell_to_snbt_pretty(spell: &Spell) -> Result<String, napi::Error> {
+    Ok(crate::spell_to_snbt_pretty(spell)?)
+}
+
+#[napi]
+pub fn spell_to_json(spell: &Spell) -> Result<String, napi::Error> {
+    spell
+        .to_json()
+        .map_err(|e| napi::Error::new(Status::GenericFailure, e))
+}
+
+#[napi]
+pub fn spell_from_json(json: String) -> Result<Spell, napi::Error> {
+    Spell::from_json(&json).map_err(|e| napi::Error::new(Status::InvalidArg, e))
+}
+
+/// Whether `name` is a parameter that is encoded as a single index byte.
+#[napi]
+pub fn is_builtin_param(name: String) -> bool {
+    crate::is_builtin_param(&name)
+}
+
+/// Parameter names that are encoded as a single index byte instead of by name.
+#[napi]
+pub fn list_builtin_params() -> Vec<String> {
+    crate::builtin_params()
+        .iter()
+        .map(|name| name.to_string())
+        .collect()
+}
+
+/// Every error and warning found in the spell, for showing in an editor.
+#[napi]
+pub fn validate_spell(spell: &Spell) -> Vec<ValidationIssue> {
+    spell.issues()
+}
@@ -0,0 +1,150 @@
+//! Splitting share strings across several QR codes.
+
+use std::fmt;
+
+use crate::{decode_spell, encode_spell, Spell, SpellError, SpellParseError};
+
+/// Error from [`Spell::from_qr_segments`].
+#[derive(Debug)]
+pub enum QrError {
+    /// There were no segments at all.
+    Empty,
+    /// A segment has no `index/count:` header, its index is out of range, its count is
+    /// zero or more than the number of segments given, or it disagrees with the others
+    /// about the count.
+    MalformedSegment(String),
+    /// The segment with this one-based index was not given.
+    MissingSegment(usize),
+    /// The reassembled string was not a valid share string.
+    Parse(SpellParseError),
+}
+
+impl fmt::Display for QrError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Empty => write!(f, "no QR segments were given"),
+            Self::MalformedSegment(segment) => write!(f, "malformed QR segment {segment:?}"),
+            Self::MissingSegment(index) => write!(f, "QR segment {index} is missing"),
+            Self::Parse(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl std::error::Error for QrError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Parse(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<SpellParseError> for QrError {
+    #[inline]
+    fn from(value: SpellParseError) -> Self {
+        Self::Parse(value)
+    }
+}
+
+/// Length of the `index/count:` header of every segment when there are `count` of
+/// them. Indices are not zero-padded, so this is the longest header.
+#[inline]
+fn header_len(count: usize) -> usize {
+    2 * count.to_string().len() + 2
+}
+
+/// Parses an `index/count:payload` segment.
+fn parse_segment(segment: &str) -> Option<(usize, usize, &str)> {
+    let (header, payload) = segment.split_once(':')?;
+    let (index, count) = header.split_once('/')?;
+    Some((index.parse().ok()?, count.parse().ok()?, payload))
+}
+
+impl Spell {
+    /// The share string for this spell, split into segments of at most `max_len`
+    /// characters for sharing as several QR codes. A string that fits is returned as
+    /// the only segment, unchanged. Otherwise every segment starts with a one-based
+    /// `index/count:` header, as in `1/3:H4sI...`.
+    ///
+    /// `max_len` is raised if needed so that each segment holds at least one
+    /// character of the share string after its header.
+    ///
+    /// ```
+    /// use psi_spell_encode::Spell;
+    ///
+    /// let spell = Spell::builder()
+    ///     .name("Blink")
+    ///     .add_piece(0, 0, "psi:trick_blink")
+    ///     .build();
+    /// let segments = spell.to_qr_segments(32).unwrap();
+    /// assert_eq!(Spell::from_qr_segments(&segments).unwrap(), spell);
+    /// ```
+    pub fn to_qr_segments(&self, max_len: usize) -> Result<Vec<String>, SpellError> {
+        let encoded = encode_spell(self)?;
+        if encoded.len() <= max_len {
+            return Ok(vec![encoded]);
+        }
+
+        // Adding segments can lengthen the header, so search for the smallest count
+        // whose segments fit the payload.
+        let mut count = 2;
+        let chunk_len = loop {
+            let chunk_len = max_len.saturating_sub(header_len(count)).max(1);
+            if (encoded.len() + chunk_len - 1) / chunk_len <= count {
+                break chunk_len;
+            }
+            count += 1;
+        };
+
+        // Share strings are ASCII, so byte chunks are character chunks.
+        let chunks: Vec<&[u8]> = encoded.as_bytes().chunks(chunk_len).collect();
+        let count = chunks.len();
+        Ok(chunks
+            .into_iter()
+            .enumerate()
+            .map(|(i, chunk)| {
+                let chunk = std::str::from_utf8(chunk).unwrap();
+                format!("{}/{count}:{chunk}", i + 1)
+            })
+            .collect())
+    }
+
+    /// Reassembles segments produced by [`Spell::to_qr_segments`], in any order, and
+    /// decodes the spell. Repeated copies of the same segment are ignored.
+    pub fn from_qr_segments<S: AsRef<str>>(segments: &[S]) -> Result<Self, QrError> {
+        match segments {
+            [] => return Err(QrError::Empty),
+            [only] if parse_segment(only.as_ref()).is_none() => {
+                return Ok(decode_spell(only.as_ref())?);
+            }
+            _ => {}
+        }
+
+        let mut parts: Vec<Option<&str>> = Vec::new();
+        for segment in segments {
+            let segment = segment.as_ref();
+            let malformed = || QrError::MalformedSegment(segment.to_owned());
+            let (index, count, payload) = parse_segment(segment).ok_or_else(malformed)?;
+            if parts.is_empty() {
+                // Checked before allocating, since the count comes from the input.
+                if count == 0 || count > segments.len() {
+                    return Err(malformed());
+                }
+                parts.resize(count, None);
+            }
+            if count != parts.len() || index == 0 || index > count {
+                return Err(malformed());
+            }
+            match parts[index - 1] {
+                Some(seen) if seen != payload => return Err(malformed()),
+                _ => parts[index - 1] = Some(payload),
+            }
+        }
+
+        let mut encoded = String::new();
+        for (i, part) in parts.into_iter().enumerate() {
+            encoded.push_str(part.ok_or(QrError::MissingSegment(i + 1))?);
+        }
+        Ok(decode_spell(&encoded)?)
+    }
+}
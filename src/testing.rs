@@ -0,0 +1,69 @@
+//! Proptest strategies for generating spells, built with the `testing` feature.
+//!
+//! Every generated spell survives [`Spell::bin`] / [`Spell::decode`] unchanged, so the
+//! strategies double as a source of valid inputs for downstream fuzzing.
+
+use proptest::{collection, option, prelude::*, sample};
+
+use crate::{builtin_params, Mod, Piece, Spell, SpellData, SpellMeta, SpellParams};
+
+pub fn arb_mod() -> impl Strategy<Value = Mod> {
+    // `,`, `;` and `]` delimit the mod list.
+    (r"[^\x00,;\]]{0,16}", r"[^\x00,;\]]{0,16}").prop_map(|(name, version)| Mod { name, version })
+}
+
+pub fn arb_param_name() -> impl Strategy<Value = String> {
+    prop_oneof![
+        sample::select(builtin_params()).prop_map(str::to_owned),
+        r"_[a-z]{1,12}",
+    ]
+}
+
+pub fn arb_spell_data() -> impl Strategy<Value = SpellData> {
+    // Params and constants are mutually exclusive on the wire, and empty params or
+    // comments decode back as `None`.
+    let body = prop_oneof![
+        Just((None::<SpellParams>, None::<String>)),
+        collection::hash_map(arb_param_name(), any::<u8>(), 1..8)
+            .prop_map(|params| (Some(params.into_iter().collect::<SpellParams>()), None)),
+        r"[^\x00]{0,16}".prop_map(|constant| (None, Some(constant))),
+    ];
+
+    (
+        r"[a-z]{1,8}:[a-z_]{0,16}",
+        option::of(r"[^\x00]{1,32}"),
+        body,
+    )
+        .prop_map(|(key, comment, (params, constant))| SpellData {
+            key,
+            params,
+            constant,
+            comment,
+            extra: None,
+        })
+}
+
+pub fn arb_meta() -> impl Strategy<Value = SpellMeta> {
+    (r"[^\x00]{0,16}", any::<i64>()).prop_map(|(author, created_unix)| SpellMeta {
+        author,
+        created_unix,
+    })
+}
+
+pub fn arb_spell() -> impl Strategy<Value = Spell> {
+    (
+        r"[^\x00]{0,32}",
+        collection::vec(arb_mod(), 0..4),
+        collection::btree_map((0..9u8, 0..9u8), arb_spell_data(), 0..16),
+        option::of(arb_meta()),
+    )
+        .prop_map(|(name, mods, pieces, meta)| Spell {
+            mods,
+            pieces: pieces
+                .into_iter()
+                .map(|((x, y), data)| Piece { data, x, y })
+                .collect(),
+            name,
+            meta,
+        })
+}
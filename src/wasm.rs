@@ -0,0 +1,41 @@
+//! Browser bindings, built with the `wasm` feature.
+
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+use crate::Spell;
+
+#[inline]
+fn to_js(spell: &Spell) -> Result<JsValue, JsError> {
+    Ok(spell.serialize(&serde_wasm_bindgen::Serializer::json_compatible())?)
+}
+
+#[inline]
+fn from_js(spell: JsValue) -> Result<Spell, JsError> {
+    Ok(serde_wasm_bindgen::from_value(spell)?)
+}
+
+#[wasm_bindgen(js_name = encodeSpell)]
+pub fn encode_spell(spell: JsValue) -> Result<String, JsError> {
+    Ok(crate::encode_spell(&from_js(spell)?)?)
+}
+
+#[wasm_bindgen(js_name = decodeSpell)]
+pub fn decode_spell(url_safe: &str) -> Result<JsValue, JsError> {
+    to_js(&crate::decode_spell(url_safe)?)
+}
+
+#[wasm_bindgen(js_name = spellFromSnbt)]
+pub fn spell_from_snbt(snbt: &str) -> Result<JsValue, JsError> {
+    to_js(&crate::spell_from_snbt(snbt)?)
+}
+
+#[wasm_bindgen(js_name = spellToSnbt)]
+pub fn spell_to_snbt(spell: JsValue) -> Result<String, JsError> {
+    Ok(crate::spell_to_snbt(&from_js(spell)?)?)
+}
+
+#[wasm_bindgen(js_name = spellToSnbtPretty)]
+pub fn spell_to_snbt_pretty(spell: JsValue) -> Result<String, JsError> {
+    Ok(crate::spell_to_snbt_pretty(&from_js(spell)?)?)
+}
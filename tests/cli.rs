@@ -0,0 +1,65 @@
+//! The `psi-spell` binary. Run with
+//! `cargo test --no-default-features --features cli --test cli`.
+
+#![cfg(feature = "cli")]
+
+use assert_cmd::Command;
+use psi_spell_encode::{encode_spell, spell_to_snbt, Side, Spell};
+
+fn psi_spell(subcommand: &str) -> Command {
+    let mut cmd = Command::cargo_bin("psi-spell").unwrap();
+    cmd.arg(subcommand);
+    cmd
+}
+
+fn sample() -> Spell {
+    Spell::builder()
+        .name("Blink")
+        .add_mod("psi", "2.0.0")
+        .add_piece(0, 0, "psi:selector_caster")
+        .add_piece(1, 0, "psi:trick_blink")
+        .param("_target", Side::Left)
+        .build()
+}
+
+fn stdout(cmd: &mut Command) -> String {
+    let output = cmd.assert().success().get_output().stdout.clone();
+    String::from_utf8(output).unwrap()
+}
+
+#[test]
+fn encode_reads_json_and_snbt() {
+    let spell = sample();
+    let expected = format!("{}\n", encode_spell(&spell).unwrap());
+    let json = spell.to_json().unwrap();
+    assert_eq!(stdout(psi_spell("encode").write_stdin(json)), expected);
+    let snbt = spell_to_snbt(&spell).unwrap();
+    assert_eq!(stdout(psi_spell("encode").write_stdin(snbt)), expected);
+}
+
+#[test]
+fn decode_prints_json() {
+    let encoded = encode_spell(&sample()).unwrap();
+    let json = stdout(psi_spell("decode").write_stdin(encoded));
+    assert_eq!(Spell::from_json(json.trim()).unwrap(), sample());
+}
+
+#[test]
+fn inspect_prints_summary() {
+    let encoded = encode_spell(&sample()).unwrap();
+    psi_spell("inspect")
+        .write_stdin(encoded)
+        .assert()
+        .success()
+        .stdout("name: Blink\nmod: psi 2.0.0\npieces: 2\n");
+}
+
+#[test]
+fn invalid_input_fails() {
+    let assert = psi_spell("decode")
+        .write_stdin("not a spell")
+        .assert()
+        .failure();
+    let stderr = String::from_utf8(assert.get_output().stderr.clone()).unwrap();
+    assert!(stderr.starts_with("error: "));
+}
@@ -0,0 +1,196 @@
+use std::io::Read;
+
+use psi_spell_encode::{
+    decode_any_to_bytes, decode_base64_to_bytes, decode_spells, decode_url_safe_deflate_to_bytes,
+    decode_url_safe_to_bytes, decode_url_safe_to_bytes_limited, encode_bytes_to_base64,
+    encode_bytes_to_base64_raw, encode_bytes_to_url_safe, encode_bytes_to_url_safe_deflate,
+    encode_reader_to_url_safe, encode_spell, encode_spell_with_stats, encode_spells, CodecError,
+    Spell, SpellError,
+};
+
+#[test]
+fn pasted_whitespace_and_padding_are_tolerated() {
+    let encoded = encode_bytes_to_url_safe(b"spell");
+    let pasted = format!("  {}  {} \n", &encoded[..6], &encoded[6..]);
+    assert_eq!(decode_url_safe_to_bytes(&pasted).unwrap(), b"spell");
+    let unpadded = encoded.trim_end_matches('=');
+    assert_eq!(decode_url_safe_to_bytes(unpadded).unwrap(), b"spell");
+}
+
+#[test]
+fn base64_alphabets_reject_each_other() {
+    let standard = encode_bytes_to_base64(b"spell");
+    let url_safe = encode_bytes_to_url_safe(b"spell");
+    // The gzip header's "unknown OS" byte, 255, starts the 13th character, which is
+    // `/` in the standard alphabet and `_` in the URL-safe one.
+    assert_eq!(&standard[12..13], "/");
+    assert_eq!(&url_safe[12..13], "_");
+
+    assert_eq!(decode_base64_to_bytes(&standard).unwrap(), b"spell");
+    assert_eq!(decode_url_safe_to_bytes(&url_safe).unwrap(), b"spell");
+    assert!(matches!(
+        decode_base64_to_bytes(&url_safe),
+        Err(CodecError::Base64(_))
+    ));
+    assert!(matches!(
+        decode_url_safe_to_bytes(&standard),
+        Err(CodecError::Base64(_))
+    ));
+}
+
+#[cfg(feature = "zstd")]
+#[test]
+fn zstd_is_shorter_than_gzip_for_large_spells() {
+    use psi_spell_encode::{decode_bytes_zstd, encode_bytes_zstd, Side};
+
+    let mut builder = Spell::builder().name("Large").add_mod("psi", "2.0.0");
+    for i in 0..81u8 {
+        builder = builder
+            .add_piece(i % 9, i / 9, "psi:operator_vector_sum")
+            .param("_vector1", Side::Left)
+            .param("_vector2", Side::Top)
+            .comment(format!("sum {i}"))
+            .done();
+    }
+    let bin = builder.build().bin();
+
+    let zstd = encode_bytes_zstd(&bin);
+    assert!(zstd.len() < encode_bytes_to_url_safe(&bin).len());
+    assert_eq!(decode_bytes_zstd(&zstd).unwrap(), bin);
+}
+
+#[test]
+fn deflate_is_shorter_than_gzip() {
+    let bin = Spell::builder()
+        .name("Tiny")
+        .add_piece(0, 0, "psi:selector_caster")
+        .build()
+        .bin();
+    // One codec tag byte replaces gzip's 10-byte header and 8-byte footer.
+    let deflate = encode_bytes_to_url_safe_deflate(&bin);
+    assert!(deflate.len() < encode_bytes_to_url_safe(&bin).len());
+    assert_eq!(decode_url_safe_deflate_to_bytes(&deflate).unwrap(), bin);
+}
+
+#[test]
+fn decode_any_detects_every_codec() {
+    let bin = Spell::builder()
+        .name("Blink")
+        .add_piece(0, 0, "psi:trick_blink")
+        .build()
+        .bin();
+    let encoded = [
+        encode_bytes_to_base64_raw(&bin),
+        encode_bytes_to_url_safe_deflate(&bin),
+        encode_bytes_to_url_safe(&bin),
+        encode_bytes_to_base64(&bin),
+        #[cfg(feature = "zstd")]
+        psi_spell_encode::encode_bytes_zstd(&bin),
+    ];
+    for encoded in encoded {
+        assert_eq!(decode_any_to_bytes(&encoded).unwrap(), bin, "{encoded}");
+    }
+}
+
+#[test]
+fn decode_any_rejects_unknown_codec() {
+    // Base64 of `7f 01 02`, which is neither gzip nor a known codec tag.
+    assert!(matches!(
+        decode_any_to_bytes("fwEC"),
+        Err(CodecError::UnknownCodec(0x7f))
+    ));
+}
+
+#[test]
+fn reader_encoding_decodes_back() {
+    let bin = Spell::builder()
+        .add_piece(0, 0, "psi:trick_blink")
+        .build()
+        .bin();
+    let encoded = encode_reader_to_url_safe(&bin[..]).unwrap();
+    assert_eq!(decode_url_safe_to_bytes(&encoded).unwrap(), bin);
+}
+
+#[test]
+fn gzip_output_is_pinned() {
+    // The 10-byte header (no mtime, fast level, unknown OS), an empty final fixed
+    // Huffman block, then the CRC32 and length of nothing.
+    assert_eq!(
+        encode_bytes_to_url_safe(b""),
+        "H4sIAAAAAAAE_wMAAAAAAAAAAAA="
+    );
+}
+
+#[test]
+fn gzip_output_matches_read_mode_encoder() {
+    let bin = Spell::builder()
+        .name("Blink")
+        .add_mod("psi", "2.0.0")
+        .add_piece(0, 0, "psi:selector_caster")
+        .add_piece(1, 0, "psi:trick_blink")
+        .comment("hop")
+        .build()
+        .bin();
+    let mut gz = flate2::read::GzEncoder::new(bin.as_slice(), flate2::Compression::fast());
+    let mut compressed = Vec::new();
+    gz.read_to_end(&mut compressed).unwrap();
+    let expected = base64_simd::URL_SAFE.encode_to_string(compressed);
+    assert_eq!(encode_bytes_to_url_safe(&bin), expected);
+}
+
+#[test]
+fn limited_decode_rejects_oversized_payload() {
+    let bomb = encode_bytes_to_url_safe(&[0; 1 << 20]);
+    // A megabyte of zeros compresses to a couple of kilobytes.
+    assert!(bomb.len() < 4096);
+    let err = decode_url_safe_to_bytes_limited(&bomb, 4096);
+    assert!(matches!(err, Err(CodecError::TooLarge(4096))));
+    let err = decode_url_safe_to_bytes_limited(&bomb, (1 << 20) - 1);
+    assert!(matches!(err, Err(CodecError::TooLarge(_))));
+
+    let decoded = decode_url_safe_to_bytes_limited(&bomb, 1 << 20).unwrap();
+    assert_eq!(decoded.len(), 1 << 20);
+}
+
+#[test]
+fn several_spells_round_trip_in_order() {
+    let spells: Vec<Spell> = ["One", "Two", "Three"]
+        .into_iter()
+        .map(|name| {
+            Spell::builder()
+                .name(name)
+                .add_mod("psi", "2.0.0")
+                .add_piece(0, 0, "psi:selector_caster")
+                .build()
+        })
+        .collect();
+    let encoded = encode_spells(&spells).unwrap();
+    assert_eq!(decode_spells(&encoded).unwrap(), spells);
+
+    let encoded = encode_spells(&[]).unwrap();
+    assert!(decode_spells(&encoded).unwrap().is_empty());
+}
+
+#[test]
+fn encode_stats_report_each_stage() {
+    let spell = Spell::builder()
+        .name("Blink")
+        .add_mod("psi", "2.0.0")
+        .add_piece(0, 0, "psi:selector_caster")
+        .add_piece(1, 0, "psi:trick_blink")
+        .comment("hop")
+        .build();
+    let (encoded, stats) = encode_spell_with_stats(&spell).unwrap();
+    assert_eq!(encoded, encode_spell(&spell).unwrap());
+    assert_eq!(stats.raw_len as usize, spell.bin().len());
+    // Padded base64 turns every started group of 3 bytes into 4 characters.
+    assert_eq!(stats.final_len, (stats.compressed_len + 2) / 3 * 4);
+    assert_eq!(stats.final_len as usize, encoded.len());
+
+    let invalid = Spell::builder()
+        .add_piece(0, 0, "psi:trick_blink")
+        .add_piece(0, 0, "psi:trick_blink")
+        .build();
+    let err = SpellError::OverlappingPieces { x: 0, y: 0 };
+    assert_eq!(encode_spell_with_stats(&invalid), Err(err));
+}
@@ -0,0 +1,134 @@
+use psi_spell_encode::{Mod, PatchError, PieceChange, PieceMove, Spell, SpellDiff};
+
+fn psi(version: &str) -> Mod {
+    Mod {
+        name: "psi".to_owned(),
+        version: version.to_owned(),
+    }
+}
+
+#[test]
+fn diff_reports_every_change() {
+    let a = Spell::builder()
+        .name("Blink")
+        .add_mod("psi", "2.0.0")
+        .add_piece(0, 0, "psi:selector_caster")
+        .comment("old")
+        .add_piece(1, 0, "psi:trick_blink")
+        .build();
+    let b = Spell::builder()
+        .name("Blink 2")
+        .add_mod("psi", "2.0.1")
+        .add_piece(0, 0, "psi:selector_caster")
+        .comment("new")
+        .add_piece(2, 0, "psi:trick_blink")
+        .build();
+
+    let expected = SpellDiff {
+        name: Some(("Blink".to_owned(), "Blink 2".to_owned())),
+        added_mods: vec![psi("2.0.1")],
+        removed_mods: vec![psi("2.0.0")],
+        added: vec![],
+        removed: vec![],
+        moved: vec![PieceMove {
+            from: (1, 0),
+            to: (2, 0),
+            data: a.pieces[1].data.clone(),
+        }],
+        changed: vec![PieceChange {
+            x: 0,
+            y: 0,
+            old: a.pieces[0].data.clone(),
+            new: b.pieces[0].data.clone(),
+        }],
+    };
+    assert_eq!(a.diff(&b), expected);
+    assert!(a.diff(&a.clone()).is_empty());
+}
+
+fn constant_and_blink(constant: &str, blink_x: u8) -> Spell {
+    Spell::builder()
+        .name("Blink")
+        .add_mod("psi", "2.0.0")
+        .add_piece(0, 0, "psi:constant_number")
+        .constant(constant)
+        .add_piece(blink_x, 0, "psi:trick_blink")
+        .build()
+}
+
+#[test]
+fn patch_turns_one_spell_into_the_other() {
+    let a = constant_and_blink("4", 1);
+    let b = constant_and_blink("8", 3);
+    let patch = a.diff(&b);
+    assert_eq!(patch.moved.len(), 1);
+    assert_eq!(patch.changed.len(), 1);
+
+    let mut patched = a.clone();
+    patched.apply_patch(&patch).unwrap();
+    assert_eq!(patched, b);
+}
+
+#[test]
+fn conflicting_patch_changes_nothing() {
+    let patch = constant_and_blink("4", 1).diff(&constant_and_blink("8", 3));
+
+    let mut other = constant_and_blink("5", 1);
+    let before = other.clone();
+    let err = PatchError::MissingPiece { x: 0, y: 0 };
+    assert_eq!(other.apply_patch(&patch), Err(err));
+    assert_eq!(other, before);
+
+    let mut occupied = constant_and_blink("4", 1);
+    let mut blocker = occupied.pieces[1].clone();
+    blocker.x = 3;
+    blocker.data.comment = Some("in the way".to_owned());
+    occupied.pieces.push(blocker);
+    let err = PatchError::Occupied { x: 3, y: 0 };
+    assert_eq!(occupied.apply_patch(&patch), Err(err));
+
+    let mut renamed = constant_and_blink("4", 1);
+    renamed.name = "Hop".to_owned();
+    let rename = SpellDiff {
+        name: Some(("Blink".to_owned(), "Blink 2".to_owned())),
+        ..SpellDiff::default()
+    };
+    let err = PatchError::NameMismatch {
+        expected: "Blink".to_owned(),
+        found: "Hop".to_owned(),
+    };
+    assert_eq!(renamed.apply_patch(&rename), Err(err));
+}
+
+#[test]
+fn similarity_ranges_from_disjoint_to_identical() {
+    let a = Spell::builder()
+        .add_piece(0, 0, "psi:selector_caster")
+        .add_piece(1, 0, "psi:trick_blink")
+        .build();
+    assert_eq!(a.similarity(&a.clone()), 1.0);
+
+    // Unqualified keys count as `psi:`, and names and mods are ignored.
+    let unqualified = Spell::builder()
+        .name("Other")
+        .add_mod("psi", "2.0.0")
+        .add_piece(0, 0, "selector_caster")
+        .add_piece(1, 0, "trick_blink")
+        .build();
+    assert_eq!(a.similarity(&unqualified), 1.0);
+
+    let disjoint = Spell::builder().add_piece(4, 4, "psi:trick_blink").build();
+    assert_eq!(a.similarity(&disjoint), 0.0);
+    assert_eq!(disjoint.similarity(&a), 0.0);
+
+    let one_of_three = Spell::builder()
+        .add_piece(0, 0, "psi:selector_caster")
+        .add_piece(4, 4, "psi:trick_blink")
+        .build();
+    assert_eq!(a.similarity(&one_of_three), 1.0 / 3.0);
+
+    let empty = Spell::empty("Empty").similarity(&Spell::empty("Also empty"));
+    assert!(!empty.is_nan());
+    assert_eq!(empty, 1.0);
+    assert_eq!(a.similarity(&Spell::empty("Empty")), 0.0);
+}
@@ -0,0 +1,63 @@
+use std::{fs, path::PathBuf, process};
+
+use psi_spell_encode::{decode_spells_from_lines, FileError, Side, Spell};
+
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("psi-spell-encode-{}-{name}", process::id()))
+}
+
+fn sample() -> Spell {
+    Spell::builder()
+        .name("Blink")
+        .add_mod("psi", "2.0.0")
+        .add_piece(0, 0, "psi:selector_caster")
+        .add_piece(1, 0, "psi:trick_blink")
+        .param("_target", Side::Left)
+        .comment("hop")
+        .build()
+}
+
+#[test]
+fn round_trips_through_every_extension() {
+    let spell = sample();
+    for extension in ["json", "snbt", "txt"] {
+        let path = temp_path(&format!("round-trip.{extension}"));
+        spell.to_file(&path).unwrap();
+        let read = Spell::from_file(&path);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(read.unwrap(), spell, "{extension}");
+    }
+}
+
+#[test]
+fn unsupported_extension_is_not_an_io_error() {
+    for name in ["spell.bin", "spell"] {
+        let path = temp_path(name);
+        let written = sample().to_file(&path);
+        assert!(matches!(written, Err(FileError::UnsupportedExtension)));
+        assert!(!path.exists());
+        // The extension is checked before the file is opened.
+        let read = Spell::from_file(&path);
+        assert!(matches!(read, Err(FileError::UnsupportedExtension)));
+    }
+}
+
+#[test]
+fn missing_file_is_an_io_error() {
+    let read = Spell::from_file(temp_path("missing.json"));
+    assert!(matches!(read, Err(FileError::Io(_))));
+}
+
+#[test]
+fn lines_decode_lazily_past_blank_and_bad_lines() {
+    let a = Spell::empty("A").to_share_string().unwrap();
+    let b = sample().to_share_string().unwrap();
+    let dump = format!("{a}\n\n   \nnot a spell\n  {b}  \n");
+    let results: Vec<_> = decode_spells_from_lines(dump.as_bytes()).collect();
+    assert_eq!(results.len(), 3);
+    assert_eq!(results[0].as_ref().unwrap(), &Spell::empty("A"));
+    assert!(matches!(results[1], Err(FileError::Parse(_))));
+    assert_eq!(results[2].as_ref().unwrap(), &sample());
+
+    assert_eq!(decode_spells_from_lines(&b"\n\n"[..]).count(), 0);
+}
@@ -0,0 +1,366 @@
+use std::borrow::Cow;
+
+use psi_spell_encode::{DecodeError, Side, Spell, SpellError, SpellMeta, SpellParams};
+
+#[test]
+fn wide_coordinates_take_one_more_byte_per_piece() {
+    let standard = Spell::builder().add_piece(8, 8, "psi:trick_blink").build();
+    let extended = Spell::builder()
+        .add_piece(20, 18, "psi:trick_blink")
+        .build();
+    assert_eq!(extended.bin().len(), standard.bin().len() + 1);
+    assert_eq!(Spell::decode(&standard.bin()).unwrap(), standard);
+    assert_eq!(Spell::decode(&extended.bin()).unwrap(), extended);
+}
+
+#[test]
+fn meta_round_trips() {
+    let mut spell = Spell::builder().add_piece(0, 0, "psi:trick_blink").build();
+    assert_eq!(Spell::decode(&spell.bin()).unwrap().meta, None);
+
+    spell.meta = Some(SpellMeta {
+        author: "Vazkii".to_owned(),
+        created_unix: 1_700_000_000,
+    });
+    assert_eq!(spell.bin().len(), spell.bin_len());
+    assert_eq!(Spell::decode(&spell.bin()).unwrap(), spell);
+}
+
+#[test]
+fn empty_params_decode_as_none() {
+    let mut spell = Spell::builder()
+        .add_piece(0, 0, "psi:constant_number")
+        .constant("4")
+        .build();
+    spell.pieces[0].data.params = Some(SpellParams::new());
+    let decoded = Spell::decode(&spell.bin()).unwrap();
+    assert_eq!(decoded.pieces[0].data.params, None);
+    assert_eq!(decoded.pieces[0].data.constant.as_deref(), Some("4"));
+}
+
+#[test]
+fn corrupted_buffer_fails_checksum() {
+    let mut bin = Spell::builder().name("Blink").build().bin();
+    bin[2] ^= 1;
+    assert_eq!(Spell::decode(&bin), Err(DecodeError::ChecksumMismatch));
+}
+
+#[test]
+fn empty_and_name_only_buffers() {
+    assert_eq!(Spell::decode(&[]), Err(DecodeError::TruncatedHeader));
+    assert_eq!(Spell::decode(b"Blink\0]").unwrap(), Spell::empty("Blink"));
+    let bin = Spell::empty("Blink").bin();
+    assert_eq!(Spell::decode(&bin).unwrap().mods, []);
+}
+
+#[test]
+fn bin_verbose_keeps_psi_prefix() {
+    let spell = Spell::builder()
+        .add_piece(0, 0, "psi:foo")
+        .add_piece(1, 0, "bar")
+        .build();
+    let has_prefix = |bin: &[u8]| bin.windows(7).any(|w| w == b"psi:foo");
+    assert!(has_prefix(&spell.bin_verbose()));
+    assert!(!has_prefix(&spell.bin()));
+}
+
+#[test]
+fn decode_ref_borrows_where_possible() {
+    let spell = Spell::builder()
+        .name("Blink")
+        .add_mod("psi", "2.0.0")
+        .add_piece(0, 0, "psi:trick_blink")
+        .param("_target", Side::Left)
+        .param("_custom", Side::Top)
+        .comment("hop")
+        .build();
+    let bin = spell.bin();
+    let view = Spell::decode_ref(&bin).unwrap();
+    assert_eq!(view.mods, [("psi", "2.0.0")]);
+    let piece = &view.pieces[0];
+    // The stripped `psi:` has to be added back, so the key is owned.
+    assert_eq!(piece.key, "psi:trick_blink");
+    assert!(matches!(piece.key, Cow::Owned(_)));
+    assert_eq!(piece.comment, Some("hop"));
+    let params = piece.params.as_ref().unwrap();
+    assert!(matches!(params[0], (Cow::Borrowed("_target"), 3)));
+    assert!(matches!(&params[1], (Cow::Owned(name), 1) if name == "_custom"));
+    assert_eq!(Spell::from(view), spell);
+}
+
+#[test]
+fn peek_name_reads_legacy_buffers() {
+    assert_eq!(Spell::peek_name(b"Legacy\0]").unwrap(), "Legacy");
+}
+
+#[test]
+fn decode_strict_rejects_invalid_side() {
+    let mut spell = Spell::builder()
+        .add_piece(0, 0, "psi:trick_blink")
+        .param("_target", Side::Top)
+        .build();
+    spell.pieces[0].data.params.as_mut().unwrap()["_target"] = 200;
+    let param = "_target".to_owned();
+    let err = SpellError::InvalidSide {
+        x: 0,
+        y: 0,
+        param,
+        side: 200,
+    };
+    assert_eq!(spell.validate(), Err(err.clone()));
+    assert_eq!(
+        Spell::decode_strict(&spell.bin()),
+        Err(DecodeError::Invalid(err))
+    );
+}
+
+#[test]
+fn decode_partial_skips_checksum_of_corrupted_buffer() {
+    let spell = Spell::builder()
+        .name("Blink")
+        .add_piece(0, 0, "psi:selector_caster")
+        .add_piece(1, 0, "psi:trick_blink")
+        .build();
+    let mut bin = spell.bin();
+    // The first byte of the name, after the version, flags and length bytes.
+    bin[3] ^= 1;
+    let (partial, errors) = Spell::decode_partial(&bin);
+    assert_eq!(partial.name, "Clink");
+    assert_eq!(partial.pieces, spell.pieces);
+    assert_eq!(errors, [DecodeError::ChecksumMismatch]);
+}
+
+#[test]
+fn bin_into_replaces_buffer_contents() {
+    let spell = Spell::builder()
+        .name("Blink")
+        .add_piece(0, 0, "psi:trick_blink")
+        .build();
+    let mut out = Spell::builder()
+        .name("A much longer name")
+        .add_piece(0, 0, "psi:selector_caster")
+        .add_piece(1, 0, "psi:operator_entity_look")
+        .build()
+        .bin();
+    spell.bin_into(&mut out);
+    assert_eq!(out, spell.bin());
+}
+
+#[test]
+fn decode_into_clears_spell_on_bad_header() {
+    let mut spell = Spell::builder()
+        .name("Stale")
+        .add_mod("psi", "2.0.0")
+        .add_piece(0, 0, "psi:trick_blink")
+        .build();
+    let mut bin = Spell::empty("Fresh").bin();
+    bin[2] ^= 1;
+    let err = Spell::decode_into(&bin, &mut spell);
+    assert_eq!(err, Err(DecodeError::ChecksumMismatch));
+    assert_eq!(spell, Spell::empty(""));
+}
+
+#[test]
+fn too_many_params_are_capped() {
+    let mut spell = Spell::builder().add_piece(0, 0, "psi:trick_blink").build();
+    let params: SpellParams = (0..300).map(|i| (format!("_p{i}"), 1)).collect();
+    spell.pieces[0].data.params = Some(params);
+
+    let err = SpellError::TooManyParams {
+        x: 0,
+        y: 0,
+        count: 300,
+    };
+    assert_eq!(spell.try_bin(), Err(err.clone()));
+    assert_eq!(spell.validate(), Err(err));
+
+    let bin = spell.bin();
+    assert_eq!(bin.len(), spell.bin_len());
+    let decoded = Spell::decode(&bin).unwrap();
+    let params = decoded.pieces[0].data.params.as_ref().unwrap();
+    assert_eq!(params.len(), Spell::MAX_PARAMS);
+    assert_eq!(params.get_index(0), Some((&"_p0".to_owned(), &1)));
+}
+
+#[test]
+fn unknown_builtin_param_index_is_an_error() {
+    // Version 2 has no checksum: an empty name, no mods, then piece `a` at (0, 0)
+    // with no comment and one param at builtin index 200.
+    let bin = b"\x02\x00]\x00a\x00\x00\x01\xc8\x01";
+    assert_eq!(Spell::decode(bin), Err(DecodeError::BadParamIndex(200)));
+}
+
+#[test]
+fn spell_without_mods_round_trips() {
+    let spell = Spell {
+        mods: vec![],
+        ..Spell::builder()
+            .name("Blink")
+            .add_piece(0, 0, "psi:trick_blink")
+            .build()
+    };
+    let bin = spell.bin();
+    assert_eq!(Spell::peek_name(&bin).unwrap(), "Blink");
+    assert_eq!(Spell::decode(&bin).unwrap(), spell);
+}
+
+#[test]
+fn short_keys_encode() {
+    let bin = Spell::builder().add_piece(0, 0, "ab").build().bin();
+    let preserved = Spell::decode_preserving_keys(&bin).unwrap();
+    assert_eq!(preserved.pieces[0].data.key, "ab");
+    assert_eq!(Spell::decode(&bin).unwrap().pieces[0].data.key, "psi:ab");
+}
+
+#[test]
+fn encoding_is_deterministic() {
+    let spell = Spell::builder()
+        .add_piece(0, 0, "psi:trick_add_motion")
+        .param("_target", Side::Left)
+        .param("_custom_b", Side::Right)
+        .param("_direction", Side::Top)
+        .param("_custom_a", Side::Bottom)
+        .param("_speed", Side::Off)
+        .build();
+    let first = spell.bin();
+    for _ in 0..100 {
+        assert_eq!(spell.clone().bin(), first);
+    }
+}
+
+#[test]
+fn write_bin_matches_bin() {
+    let spell = Spell::builder()
+        .name("Blink")
+        .add_mod("psi", "2.0.0")
+        .add_piece(0, 0, "psi:selector_caster")
+        .add_piece(1, 0, "psi:trick_blink")
+        .param("_target", Side::Left)
+        .comment("hop")
+        .build();
+    let mut out = Vec::new();
+    spell.write_bin(&mut out).unwrap();
+    assert_eq!(out, spell.bin());
+}
+
+#[test]
+fn unprefixed_keys_get_psi_namespace() {
+    // Version 2: an empty name, no mods, then `trick_blink` at (0, 0) with no data.
+    let spell = Spell::decode(b"\x02\x00]\x00trick_blink\x00\x00\xfe").unwrap();
+    assert_eq!(spell.pieces[0].data.key, "psi:trick_blink");
+
+    let spell = Spell::decode(b"\x02\x00]\x00phi:trick_blink\x00\x00\xfe").unwrap();
+    assert_eq!(spell.pieces[0].data.key, "phi:trick_blink");
+}
+
+#[test]
+fn invalid_utf8_names_the_field() {
+    // Version 2: an empty name and no mods, then one piece at (0, 0) with no data.
+    let key = b"\x02\x00]\x00\xff\x00\x00\xfe";
+    let err = DecodeError::InvalidUtf8 { field: "piece key" };
+    assert_eq!(Spell::decode(key), Err(err));
+
+    let comment = b"\x02\x00]\x00a\x00\x01\xff\xfe";
+    let err = DecodeError::InvalidUtf8 { field: "comment" };
+    assert_eq!(Spell::decode(comment), Err(err));
+}
+
+#[test]
+fn nul_in_name_and_comment_round_trips() {
+    let spell = Spell::builder()
+        .name("Bl\0ink")
+        .add_piece(0, 0, "psi:trick_blink")
+        .comment("a\0b")
+        .build();
+    assert_eq!(spell.validate(), Ok(()));
+    let decoded = Spell::decode(&spell.bin()).unwrap();
+    assert_eq!(decoded.pieces[0].data.comment.as_deref(), Some("a\0b"));
+    assert_eq!(decoded, spell);
+}
+
+#[test]
+fn legacy_buffers_may_skip_the_empty_comment_nul() {
+    let blink = Spell::builder()
+        .name("Blink")
+        .add_piece(0, 0, "psi:trick_blink")
+        .build();
+    // Unversioned: a NUL-terminated name, no mods, then `trick_blink` at (0, 0) with
+    // an empty NUL-terminated comment and no data.
+    let with_nul = b"Blink\0]\x00trick_blink\0\0\xfe";
+    assert_eq!(Spell::decode_legacy(with_nul).unwrap(), blink);
+    assert_eq!(Spell::decode(with_nul).unwrap(), blink);
+    // The same without the comment's NUL.
+    let without_nul = b"Blink\0]\x00trick_blink\0\xfe";
+    assert_eq!(Spell::decode_legacy(without_nul).unwrap(), blink);
+
+    // `constant_number` at (1, 0) holding `8`, also without the comment's NUL.
+    let constant = b"Blink\0]\x10constant_number\0\xff8\0";
+    let expected = Spell::builder()
+        .name("Blink")
+        .add_piece(1, 0, "psi:constant_number")
+        .constant("8")
+        .build();
+    assert_eq!(Spell::decode_legacy(constant).unwrap(), expected);
+
+    let commented = b"Blink\0]\x00trick_blink\0hop\0\xfe";
+    let spell = Spell::decode_legacy(commented).unwrap();
+    assert_eq!(spell.pieces[0].data.comment.as_deref(), Some("hop"));
+
+    // A piece with params still needs the NUL: one builtin param, `_target`, on the left.
+    let params = b"Blink\0]\x00trick_blink\0\0\x01\x00\x03";
+    let expected = Spell::builder()
+        .name("Blink")
+        .add_piece(0, 0, "psi:trick_blink")
+        .param("_target", Side::Left)
+        .build();
+    assert_eq!(Spell::decode_legacy(params).unwrap(), expected);
+}
+
+#[test]
+fn default_namespace_applies_to_unqualified_keys() {
+    let spell = Spell::builder()
+        .add_piece(0, 0, "psi:trick_blink")
+        .add_piece(1, 0, "phi:trick_unknown")
+        .build();
+    let bin = spell.bin();
+    let keys = |namespace| {
+        let decoded = Spell::decode_with_default_namespace(&bin, namespace).unwrap();
+        decoded
+            .pieces
+            .into_iter()
+            .map(|p| p.data.key)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(keys("psi"), ["psi:trick_blink", "phi:trick_unknown"]);
+    assert_eq!(keys("phi"), ["phi:trick_blink", "phi:trick_unknown"]);
+    assert_eq!(Spell::decode(&bin).unwrap(), spell);
+
+    // Verbose buffers keep `psi:`, so there is nothing to add a namespace to.
+    let verbose = spell.bin_verbose();
+    let decoded = Spell::decode_with_default_namespace(&verbose, "phi").unwrap();
+    assert_eq!(decoded, spell);
+}
+
+#[test]
+fn preserving_keys_leaves_unqualified_keys_alone() {
+    // Version 2: an empty name, no mods, then `trick_blink` at (0, 0) with no data.
+    let bin = b"\x02\x00]\x00trick_blink\x00\x00\xfe";
+    let preserved = Spell::decode_preserving_keys(bin).unwrap();
+    assert_eq!(preserved.pieces[0].data.key, "trick_blink");
+
+    let spell = Spell::builder()
+        .add_piece(0, 0, "foo")
+        .add_piece(1, 0, "psi:bar")
+        .add_piece(2, 0, "phi:baz")
+        .build();
+    let keys = |bin: &[u8]| {
+        let decoded = Spell::decode_preserving_keys(bin).unwrap();
+        decoded
+            .pieces
+            .into_iter()
+            .map(|p| p.data.key)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(keys(&spell.bin()), ["foo", "bar", "phi:baz"]);
+    assert_eq!(keys(&spell.bin_verbose()), ["foo", "psi:bar", "phi:baz"]);
+}
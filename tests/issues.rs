@@ -0,0 +1,59 @@
+use psi_spell_encode::{IssueSeverity, Side, Spell, SpellParams};
+
+#[test]
+fn long_name_is_a_warning() {
+    let spell = Spell::empty("a".repeat(Spell::MAX_NAME_LEN + 1));
+    assert_eq!(spell.validate(), Ok(()));
+    let issues = spell.issues();
+    assert_eq!(issues.len(), 1);
+    assert_eq!(issues[0].severity, IssueSeverity::Warning);
+    assert_eq!(issues[0].code, "name_too_long");
+    assert_eq!((issues[0].x, issues[0].y), (None, None));
+}
+
+#[test]
+fn every_issue_kind_is_reported_in_order() {
+    let mut spell = Spell::builder()
+        .name("a".repeat(Spell::MAX_NAME_LEN + 1))
+        .add_mod("psi", "1.0;beta")
+        .add_piece(9, 0, "psi:trick_blink")
+        .add_piece(1, 0, "psi:trick_add_motion")
+        .add_piece(2, 0, "psi:trick_blink")
+        .param("_target", Side::Top)
+        .add_piece(3, 0, "psi:constant_number")
+        .constant("a\0b")
+        .add_piece(4, 0, "psi:selector_caster")
+        .add_piece(4, 0, "psi:selector_caster")
+        .add_piece(5, 0, "phi:trick_unknown")
+        .param("_target", Side::Off)
+        .build();
+    let params: SpellParams = (0..300).map(|i| (format!("_p{i}"), 1)).collect();
+    spell.pieces[1].data.params = Some(params);
+    spell.pieces[2].data.params.as_mut().unwrap()["_target"] = 200;
+
+    let issues: Vec<_> = spell
+        .issues()
+        .into_iter()
+        .map(|issue| (issue.severity, issue.code, issue.x, issue.y))
+        .collect();
+    let issue = |severity, code: &str, pos: Option<(u8, u8)>| {
+        let (x, y) = (pos.map(|(x, _)| x), pos.map(|(_, y)| y));
+        (severity, code.to_owned(), x, y)
+    };
+    let error = |code, pos| issue(IssueSeverity::Error, code, pos);
+    let warning = |code, pos| issue(IssueSeverity::Warning, code, pos);
+    assert_eq!(
+        issues,
+        [
+            error("reserved_byte_in_mod", None),
+            error("outside_grid", Some((9, 0))),
+            error("too_many_params", Some((1, 0))),
+            error("invalid_side", Some((2, 0))),
+            error("nul_in_field", Some((3, 0))),
+            error("overlapping_pieces", Some((4, 0))),
+            warning("name_too_long", None),
+            warning("unconnected_param", Some((5, 0))),
+            warning("undeclared_mod", None),
+        ]
+    );
+}
@@ -0,0 +1,49 @@
+use psi_spell_encode::Spell;
+use serde_json::Value;
+
+#[test]
+fn snake_case_keys_are_accepted() {
+    let piece = r#"{"x": 1, "y": 0, "data": {"key": "psi:trick_blink"}}"#;
+    let camel = format!(r#"{{"spellName": "", "modsRequired": [], "spellList": [{piece}]}}"#);
+    let snake = format!(r#"{{"spell_name": "", "mods_required": [], "spell_list": [{piece}]}}"#);
+    let spell = Spell::from_json(&snake).unwrap();
+    assert_eq!(spell.pieces.len(), 1);
+    assert_eq!(spell, Spell::from_json(&camel).unwrap());
+}
+
+#[test]
+fn params_are_read_from_object_or_pairs() {
+    let object = r#"{"spellName": "", "modsRequired": [], "spellList": [{"x": 0, "y": 0,
+        "data": {"key": "psi:trick_blink", "params": {"_target": 3, "_distance": 1}}}]}"#;
+    let pairs = r#"{"spellName": "", "modsRequired": [], "spellList": [{"x": 0, "y": 0,
+        "data": {"key": "psi:trick_blink", "params": [["_target", 3], ["_distance", 1]]}}]}"#;
+    assert_eq!(
+        Spell::from_json(object).unwrap(),
+        Spell::from_json(pairs).unwrap()
+    );
+}
+
+#[test]
+fn unset_fields_are_omitted_and_null_is_accepted() {
+    let spell = Spell::builder().add_piece(0, 0, "psi:trick_blink").build();
+    let json = spell.to_json().unwrap();
+    let piece = r#"{"data":{"key":"psi:trick_blink"},"x":0,"y":0}"#;
+    assert!(json.contains(piece));
+    let with_null = r#"{"data":{"key":"psi:trick_blink","comment":null},"x":0,"y":0}"#;
+    assert_eq!(
+        Spell::from_json(&json.replace(piece, with_null)).unwrap(),
+        spell
+    );
+}
+
+#[test]
+fn exported_spell_round_trips() {
+    let export = include_str!("fixtures/blink.json");
+    let spell = Spell::from_json(export).unwrap();
+    assert_eq!(spell.name, "Blink");
+    assert_eq!(spell.pieces.len(), 4);
+
+    let json: Value = serde_json::from_str(&spell.to_json().unwrap()).unwrap();
+    assert_eq!(json, serde_json::from_str::<Value>(export).unwrap());
+    assert_eq!(Spell::decode(&spell.bin()).unwrap(), spell);
+}
@@ -0,0 +1,206 @@
+use psi_spell_encode::{
+    bin_to_nbt, nbt_to_bin, spell_from_nbt, spell_from_psi_export, spell_from_snbt, spell_to_nbt,
+    spell_to_psi_export, spell_to_snbt, spell_to_snbt_pretty, NbtCompound, NbtError, NbtTag, Side,
+    Spell,
+};
+
+#[test]
+fn wrong_field_type_is_reported() {
+    let err = spell_from_snbt(r#"{spellName: "x", spellList: 1b, modsRequired: []}"#);
+    assert!(matches!(
+        err,
+        Err(NbtError::WrongType {
+            field: "spellList",
+            ..
+        })
+    ));
+}
+
+#[test]
+fn unknown_root_tags_are_ignored() {
+    let spell = Spell::builder()
+        .name("Blink")
+        .add_mod("psi", "2.0.0")
+        .add_piece(0, 0, "psi:selector_caster")
+        .build();
+    let mut nbt = spell_to_nbt(&spell).unwrap();
+    if let NbtTag::Compound(root) = &mut nbt {
+        root.insert("uuidMost", 1i64);
+    }
+    let read = spell_from_nbt(&nbt).unwrap();
+    assert_eq!(read, spell);
+    assert_eq!(spell_to_nbt(&read).unwrap(), spell_to_nbt(&spell).unwrap());
+}
+
+#[test]
+fn unknown_piece_tags_are_kept() {
+    let snbt = r#"{
+        spellName: "x",
+        modsRequired: [],
+        spellList: [{x: 0b, y: 0b, data: {key: "psi:selector_caster", addonTag: "kept"}}]
+    }"#;
+    let spell = spell_from_snbt(snbt).unwrap();
+    let extra = spell.pieces[0].data.extra.as_ref().unwrap();
+    assert_eq!(extra["addonTag"], "kept");
+    assert!(spell_to_snbt(&spell).unwrap().contains("addonTag"));
+}
+
+#[test]
+fn reads_psi_export() {
+    let export = include_str!("fixtures/blink.psi");
+    let spell = spell_from_psi_export(export).unwrap();
+    let expected = Spell::builder()
+        .name("Blink")
+        .add_mod("psi", "2.0.0")
+        .add_piece(3, 4, "psi:selector_caster")
+        .add_piece(4, 4, "psi:operator_entity_look")
+        .param("_target", Side::Left)
+        .add_piece(5, 3, "psi:constant_number")
+        .constant("8")
+        .add_piece(5, 4, "psi:trick_blink")
+        .param("_target", Side::Left)
+        .param("_distance", Side::Top)
+        .comment("Blink where I look")
+        .build();
+    assert_eq!(spell, expected);
+    let export = spell_to_psi_export(&spell).unwrap();
+    assert_eq!(spell_from_psi_export(&export).unwrap(), spell);
+}
+
+#[test]
+fn writes_psi_field_names() {
+    let spell = Spell::builder()
+        .name("Blink")
+        .add_mod("psi", "2.0.0")
+        .add_piece(0, 0, "psi:constant_number")
+        .constant("8")
+        .comment("hop")
+        .add_piece(1, 0, "psi:trick_blink")
+        .param("_target", Side::Left)
+        .build();
+    let root = spell_to_nbt(&spell).unwrap();
+    let root = compound(&root);
+    assert_eq!(keys(root), ["modsRequired", "spellList", "spellName"]);
+    let mods = list(&root.inner()["modsRequired"]);
+    assert_eq!(keys(compound(&mods[0])), ["modName", "modVersion"]);
+
+    let pieces = list(&root.inner()["spellList"]);
+    for piece in pieces {
+        assert_eq!(keys(compound(piece)), ["data", "x", "y"]);
+    }
+    let data = |i: usize| keys(compound(&compound(&pieces[i]).inner()["data"]));
+    assert_eq!(data(0), ["comment", "constant_value", "key"]);
+    assert_eq!(data(1), ["key", "params"]);
+}
+
+fn compound(tag: &NbtTag) -> &NbtCompound {
+    match tag {
+        NbtTag::Compound(compound) => compound,
+        _ => panic!("expected a compound, got {tag}"),
+    }
+}
+
+fn list(tag: &NbtTag) -> &[NbtTag] {
+    match tag {
+        NbtTag::List(list) => list.inner(),
+        _ => panic!("expected a list, got {tag}"),
+    }
+}
+
+fn keys(compound: &NbtCompound) -> Vec<&str> {
+    let mut keys: Vec<_> = compound.inner().keys().map(String::as_str).collect();
+    keys.sort_unstable();
+    keys
+}
+
+#[test]
+fn pretty_snbt_matches_golden_file() {
+    let spell = Spell::builder()
+        .name("Blink")
+        .add_mod("psi", "2.0.0")
+        .add_piece(0, 0, "psi:selector_caster")
+        .add_piece(1, 0, "psi:trick_blink")
+        .param("_target", Side::Left)
+        .comment("hop")
+        .build();
+    let golden = include_str!("fixtures/blink.snbt");
+    assert_eq!(spell_to_snbt_pretty(&spell).unwrap(), golden.trim_end());
+    assert_eq!(spell_from_snbt(golden).unwrap(), spell);
+}
+
+#[test]
+fn unknown_piece_tags_keep_their_values() {
+    let snbt = r#"{
+        spellName: "x",
+        modsRequired: [],
+        spellList: [{x: 0b, y: 0b, data: {
+            key: "psi:selector_caster",
+            addonByte: 1b,
+            addonShort: 3s,
+            addonFloat: 1.5f,
+            addonList: [1b, 2b]
+        }}]
+    }"#;
+    let spell = spell_from_snbt(snbt).unwrap();
+    let extra = spell.pieces[0].data.extra.as_ref().unwrap();
+    assert_eq!(extra["addonByte"], 1);
+    assert_eq!(extra["addonShort"], 3);
+    assert_eq!(extra["addonFloat"], 1.5);
+    assert_eq!(extra["addonList"], serde_json::json!([1, 2]));
+    let written = spell_to_snbt(&spell).unwrap();
+    assert_eq!(spell_from_snbt(&written).unwrap(), spell);
+
+    // The values survive, but not the tag types.
+    let nbt = spell_to_nbt(&spell).unwrap();
+    let piece = compound(&list(&compound(&nbt).inner()["spellList"])[0]);
+    let data = compound(&piece.inner()["data"]).inner();
+    assert_eq!(data["addonByte"], NbtTag::Long(1));
+    assert_eq!(data["addonShort"], NbtTag::Long(3));
+    assert_eq!(data["addonFloat"], NbtTag::Double(1.5));
+}
+
+#[test]
+fn bin_and_nbt_convert_both_ways() {
+    let spell = Spell::builder()
+        .name("Blink")
+        .add_mod("psi", "2.0.0")
+        .add_piece(0, 0, "psi:selector_caster")
+        .add_piece(1, 0, "psi:trick_blink")
+        .param("_target", Side::Left)
+        .comment("hop")
+        .build();
+    let bin = spell.bin();
+    let nbt = bin_to_nbt(&bin).unwrap();
+    assert_eq!(nbt, spell_to_nbt(&spell).unwrap());
+    assert_eq!(nbt_to_bin(&nbt).unwrap(), bin);
+    assert_eq!(bin_to_nbt(&nbt_to_bin(&nbt).unwrap()).unwrap(), nbt);
+
+    // Tags without a field are dropped on the way to the binary format.
+    let mut tagged = nbt.clone();
+    if let NbtTag::Compound(root) = &mut tagged {
+        root.insert("uuidMost", 1i64);
+    }
+    assert_eq!(nbt_to_bin(&tagged).unwrap(), bin);
+
+    assert!(nbt_to_bin(&NbtTag::Int(1)).is_err());
+    assert!(bin_to_nbt(b"\x02").is_err());
+}
+
+#[test]
+fn missing_root_tags_are_reported() {
+    let cases = [
+        (r#"{spellList: [], modsRequired: []}"#, "spellName"),
+        (r#"{spellName: "x", modsRequired: []}"#, "spellList"),
+        (r#"{spellName: "x", spellList: []}"#, "modsRequired"),
+    ];
+    for (snbt, field) in cases {
+        let err = spell_from_snbt(snbt);
+        assert!(
+            matches!(err, Err(NbtError::MissingField(f)) if f == field),
+            "{field}: {err:?}"
+        );
+    }
+
+    let snake_case = r#"{spell_name: "x", spell_list: [], mods_required: []}"#;
+    assert_eq!(spell_from_snbt(snake_case).unwrap(), Spell::empty("x"));
+}
@@ -0,0 +1,53 @@
+use psi_spell_encode::{QrError, Spell};
+
+fn big_spell() -> Spell {
+    let mut builder = Spell::builder().name("Big");
+    for i in 0..20u8 {
+        builder = builder.add_piece(i % 9, i / 9, "psi:trick_blink").done();
+    }
+    builder.build()
+}
+
+#[test]
+fn three_segments_reassemble_in_any_order() {
+    let spell = big_spell();
+    let encoded = spell.to_share_string().unwrap();
+    let max_len = encoded.len() / 3 + 5;
+    let segments = spell.to_qr_segments(max_len).unwrap();
+    assert_eq!(segments.len(), 3);
+    assert!(segments[0].starts_with("1/3:"));
+    assert!(segments.iter().all(|s| s.len() <= max_len));
+
+    let scanned = [&segments[2], &segments[0], &segments[1]];
+    assert_eq!(Spell::from_qr_segments(&scanned).unwrap(), spell);
+}
+
+#[test]
+fn short_share_string_is_a_single_segment() {
+    let spell = Spell::empty("Blink");
+    let segments = spell.to_qr_segments(1000).unwrap();
+    assert_eq!(segments, [spell.to_share_string().unwrap()]);
+    assert_eq!(Spell::from_qr_segments(&segments).unwrap(), spell);
+}
+
+#[test]
+fn oversized_count_is_malformed() {
+    for segment in ["1/18446744073709551615:x", "1/4000000000:x", "1/0:x"] {
+        let err = Spell::from_qr_segments(&[segment]);
+        assert!(
+            matches!(&err, Err(QrError::MalformedSegment(s)) if s == segment),
+            "{segment}: {err:?}"
+        );
+    }
+}
+
+#[test]
+fn missing_segment_is_reported() {
+    let segments = big_spell().to_qr_segments(40).unwrap();
+    let count = segments.len();
+    let mut scanned = segments.clone();
+    // Repeating the first segment keeps the number given equal to the count.
+    scanned[count - 1] = segments[0].clone();
+    let err = Spell::from_qr_segments(&scanned);
+    assert!(matches!(err, Err(QrError::MissingSegment(i)) if i == count));
+}
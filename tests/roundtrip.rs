@@ -0,0 +1,17 @@
+#![cfg(feature = "testing")]
+
+use proptest::prelude::*;
+use psi_spell_encode::{decode_spell, encode_spell, testing::arb_spell, Spell};
+
+proptest! {
+    #[test]
+    fn bin_round_trips(spell in arb_spell()) {
+        prop_assert_eq!(Spell::decode(&spell.bin()).unwrap(), spell);
+    }
+
+    #[test]
+    fn share_string_round_trips(spell in arb_spell()) {
+        let encoded = encode_spell(&spell).unwrap();
+        prop_assert_eq!(decode_spell(&encoded).unwrap(), spell);
+    }
+}
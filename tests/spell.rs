@@ -0,0 +1,677 @@
+use psi_spell_encode::{
+    builtin_param_index, builtin_params, decode_spell, encode_spell, is_builtin_param,
+    ConstantValue, PieceKind, Side, Spell, SpellError, SpellMeta, SpellParams,
+};
+
+#[test]
+fn empty_params_are_empty_kind() {
+    let mut spell = Spell::builder().add_piece(0, 0, "psi:connector").build();
+    spell.pieces[0].data.params = Some(SpellParams::new());
+    assert_eq!(spell.pieces[0].data.kind(), PieceKind::Empty);
+}
+
+#[test]
+fn nul_in_param_name_cannot_be_encoded() {
+    let spell = Spell::builder()
+        .add_piece(0, 0, "psi:trick_blink")
+        .param("a\0b", Side::Left)
+        .build();
+    let err = SpellError::NulInField {
+        x: 0,
+        y: 0,
+        field: "param name",
+    };
+    assert_eq!(encode_spell(&spell), Err(err));
+}
+
+#[test]
+fn append_horizontal_rejects_overlap() {
+    let mut spell = Spell::builder()
+        .add_piece(0, 0, "psi:selector_caster")
+        .build();
+    let other = Spell::builder().add_piece(0, 0, "psi:trick_blink").build();
+    let before = spell.clone();
+    let err = spell.append_horizontal(&other, 0);
+    assert_eq!(err, Err(SpellError::OverlappingPieces { x: 0, y: 0 }));
+    assert_eq!(spell, before);
+}
+
+#[test]
+fn append_horizontal_rejects_overlap_within_other() {
+    let mut spell = Spell::builder()
+        .add_piece(0, 0, "psi:selector_caster")
+        .build();
+    let other = Spell::builder()
+        .add_piece(0, 0, "psi:operator_entity_look")
+        .add_piece(0, 0, "psi:trick_blink")
+        .build();
+    let before = spell.clone();
+    let err = spell.append_horizontal(&other, 1);
+    assert_eq!(err, Err(SpellError::OverlappingPieces { x: 1, y: 0 }));
+    assert_eq!(spell, before);
+}
+
+#[test]
+fn append_horizontal_merges_mods() {
+    let mut spell = Spell::builder()
+        .add_mod("psi", "2.0.0")
+        .add_piece(0, 0, "psi:selector_caster")
+        .build();
+    let other = Spell::builder()
+        .add_mod("psi", "2.0.1")
+        .add_mod("phi", "1.0")
+        .add_piece(0, 0, "phi:trick_unknown")
+        .build();
+    spell.append_horizontal(&other, 1).unwrap();
+    let cells: Vec<_> = spell.pieces.iter().map(|p| (p.x, p.y)).collect();
+    assert_eq!(cells, [(0, 0), (1, 0)]);
+    let mods: Vec<_> = spell.mods.iter().map(|m| (&*m.name, &*m.version)).collect();
+    assert_eq!(mods, [("psi", "2.0.0"), ("phi", "1.0")]);
+}
+
+#[test]
+fn append_horizontal_rejects_overflow() {
+    let mut spell = Spell::builder()
+        .add_piece(0, 0, "psi:selector_caster")
+        .build();
+    let other = Spell::builder()
+        .add_piece(0, 0, "psi:operator_entity_look")
+        .add_piece(10, 2, "psi:trick_blink")
+        .build();
+    let before = spell.clone();
+    let err = spell.append_horizontal(&other, 250);
+    assert_eq!(err, Err(SpellError::OutOfGrid { x: 10, y: 2 }));
+    assert_eq!(spell, before);
+}
+
+#[test]
+fn constants_parse_as_numbers_vectors_or_raw() {
+    let constant = |value: &str| {
+        let spell = Spell::builder()
+            .add_piece(0, 0, "psi:constant_number")
+            .constant(value)
+            .build();
+        spell.pieces[0].data.parse_constant()
+    };
+    assert_eq!(constant("3.5"), Some(ConstantValue::Number(3.5)));
+    assert_eq!(
+        constant("0,1,0"),
+        Some(ConstantValue::Vector3([0.0, 1.0, 0.0]))
+    );
+    assert_eq!(constant("abc"), Some(ConstantValue::Raw("abc".to_owned())));
+    for value in ["inf", "NaN", "infinity", "1,inf,0"] {
+        assert_eq!(constant(value), Some(ConstantValue::Raw(value.to_owned())));
+    }
+
+    let spell = Spell::builder().add_piece(0, 0, "psi:connector").build();
+    assert_eq!(spell.pieces[0].data.parse_constant(), None);
+}
+
+#[test]
+fn dedup_pieces_keeps_first_copy_and_order() {
+    let mut spell = Spell::builder()
+        .add_piece(1, 1, "psi:trick_blink")
+        .add_piece(2, 1, "psi:selector_caster")
+        .add_piece(1, 1, "psi:trick_blink")
+        .add_piece(1, 1, "psi:trick_add_motion")
+        .add_piece(2, 1, "psi:selector_caster")
+        .add_piece(3, 1, "psi:trick_blink")
+        .build();
+    assert_eq!(spell.dedup_pieces(), 2);
+    let pieces: Vec<_> = spell
+        .pieces
+        .iter()
+        .map(|p| (p.x, p.y, p.data.key.as_str()))
+        .collect();
+    assert_eq!(
+        pieces,
+        [
+            (1, 1, "psi:trick_blink"),
+            (2, 1, "psi:selector_caster"),
+            (1, 1, "psi:trick_add_motion"),
+            (3, 1, "psi:trick_blink"),
+        ]
+    );
+    assert_eq!(spell.dedup_pieces(), 0);
+}
+
+#[test]
+fn builtin_param_index_looks_up_names() {
+    assert_eq!(builtin_param_index("_target"), Some(0));
+    assert_eq!(builtin_param_index("_no_such_param"), None);
+    for (i, name) in builtin_params().iter().enumerate() {
+        assert_eq!(builtin_param_index(name), Some(i as u8));
+    }
+}
+
+#[test]
+fn sides_round_trip_through_bytes() {
+    for side in Side::ALL {
+        assert_eq!(Side::from_u8(side.as_u8()), Some(side));
+    }
+    let bytes: Vec<_> = Side::ALL.iter().map(|side| side.as_u8()).collect();
+    assert_eq!(bytes, [0, 1, 2, 3, 4]);
+    assert_eq!(Side::from_u8(5), None);
+}
+
+#[test]
+fn undeclared_namespaces_are_listed() {
+    let mut spell = Spell::builder().add_piece(0, 0, "phi:something").build();
+    assert!(spell.mods.is_empty());
+    assert_eq!(spell.namespaces().into_iter().collect::<Vec<_>>(), ["phi"]);
+    assert_eq!(spell.missing_mod_declarations(), ["phi"]);
+
+    spell.pieces.extend(
+        Spell::builder()
+            .add_piece(1, 0, "psi:trick_blink")
+            .add_piece(2, 0, "trick_blink")
+            .build()
+            .pieces,
+    );
+    assert_eq!(
+        spell.namespaces().into_iter().collect::<Vec<_>>(),
+        ["phi", "psi"]
+    );
+    assert_eq!(spell.missing_mod_declarations(), ["phi"]);
+}
+
+#[test]
+fn rows_are_sorted_by_y_then_x() {
+    let spell = Spell::builder()
+        .add_piece(4, 2, "psi:trick_blink")
+        .add_piece(1, 2, "psi:operator_entity_look")
+        .add_piece(0, 0, "psi:selector_caster")
+        .add_piece(1, 2, "psi:constant_number")
+        .build();
+    let rows: Vec<(u8, Vec<&str>)> = spell
+        .rows()
+        .into_iter()
+        .map(|(y, row)| (y, row.iter().map(|p| p.data.key.as_str()).collect()))
+        .collect();
+    assert_eq!(
+        rows,
+        [
+            (0, vec!["psi:selector_caster"]),
+            (
+                2,
+                vec![
+                    "psi:operator_entity_look",
+                    "psi:constant_number",
+                    "psi:trick_blink"
+                ]
+            ),
+        ]
+    );
+}
+
+fn canonical_pair() -> (Spell, Spell) {
+    let a = Spell::builder()
+        .name("Blink")
+        .add_mod("psi", "2.0.0")
+        .add_mod("phi", "1.0")
+        .add_piece(0, 0, "psi:selector_caster")
+        .add_piece(1, 0, "psi:trick_blink")
+        .param("_target", Side::Left)
+        .param("_distance", Side::Top)
+        .param("_custom", Side::Bottom)
+        .comment("blink")
+        .build();
+    let b = Spell::builder()
+        .name("Blink")
+        .add_mod("phi", "1.0")
+        .add_mod("psi", "2.0.0")
+        .add_piece(1, 0, "trick_blink")
+        .param("_custom", Side::Bottom)
+        .param("_distance", Side::Top)
+        .param("_target", Side::Left)
+        .comment("  blink \n")
+        .add_piece(0, 0, "selector_caster")
+        .build();
+    (a, b)
+}
+
+#[test]
+fn canonical_bytes_ignore_order_prefixes_and_whitespace() {
+    let (a, b) = canonical_pair();
+    assert_ne!(a.bin(), b.bin());
+    assert_eq!(a.canonical_bytes(), b.canonical_bytes());
+}
+
+#[cfg(feature = "hash")]
+#[test]
+fn content_hash_matches_for_equivalent_spells() {
+    let (a, mut b) = canonical_pair();
+    assert_eq!(a.content_hash(), b.content_hash());
+    b.name.push('!');
+    assert_ne!(a.content_hash(), b.content_hash());
+}
+
+#[test]
+fn stripping_comments_shrinks_the_encoding() {
+    let spell = Spell::builder()
+        .add_piece(0, 0, "psi:selector_caster")
+        .comment("who is casting")
+        .add_piece(1, 0, "psi:trick_blink")
+        .comment("and where they go")
+        .build();
+    let stripped = spell.without_comments();
+    assert!(spell.pieces.iter().all(|p| p.data.comment.is_some()));
+    assert!(stripped.pieces.iter().all(|p| p.data.comment.is_none()));
+    assert!(stripped.bin().len() < spell.bin().len());
+
+    let mut in_place = spell.clone();
+    in_place.strip_comments();
+    assert_eq!(in_place, stripped);
+}
+
+#[test]
+fn blank_mod_versions_round_trip() {
+    let mut spell = Spell::builder()
+        .name("Blink")
+        .add_mod("psi", "2.0.0")
+        .add_mod("phi", "1.1")
+        .add_piece(0, 0, "psi:trick_blink")
+        .build();
+    spell.clear_mod_versions();
+    assert!(spell.mods.iter().all(|m| m.version.is_empty()));
+    assert_eq!(Spell::decode(&spell.bin()).unwrap(), spell);
+
+    spell.set_mod_version("phi", "1.2");
+    assert_eq!(spell.mods[0].version, "");
+    assert_eq!(spell.mods[1].version, "1.2");
+    assert_eq!(Spell::decode(&spell.bin()).unwrap(), spell);
+}
+
+#[test]
+fn piece_at_finds_first_piece_in_cell() {
+    let mut spell = Spell::builder()
+        .add_piece(0, 0, "psi:selector_caster")
+        .add_piece(2, 1, "psi:trick_blink")
+        .add_piece(2, 1, "psi:trick_add_motion")
+        .build();
+    assert_eq!(spell.piece_at(2, 1).unwrap().data.key, "psi:trick_blink");
+    assert!(spell.piece_at(1, 0).is_none());
+    assert!(spell.piece_at_mut(1, 0).is_none());
+
+    spell.piece_at_mut(0, 0).unwrap().data.comment = Some("me".to_owned());
+    assert_eq!(spell.pieces[0].data.comment.as_deref(), Some("me"));
+}
+
+#[test]
+fn normalize_origin_keeps_layout() {
+    let mut spell = Spell::builder()
+        .add_piece(2, 3, "psi:selector_caster")
+        .add_piece(4, 3, "psi:trick_blink")
+        .build();
+    spell.normalize_origin();
+    let cells: Vec<_> = spell.pieces.iter().map(|p| (p.x, p.y)).collect();
+    assert_eq!(cells, [(0, 0), (2, 0)]);
+
+    let mut empty = Spell::empty("Empty");
+    empty.normalize_origin();
+    assert_eq!(empty, Spell::empty("Empty"));
+}
+
+#[test]
+fn truncate_name_keeps_whole_characters() {
+    // Each `é` is two bytes, so a byte-based cut at 20 would land mid-character.
+    let mut spell = Spell::empty("é".repeat(Spell::MAX_NAME_LEN + 1));
+    spell.truncate_name();
+    assert_eq!(spell.name, "é".repeat(Spell::MAX_NAME_LEN));
+
+    let mut spell = Spell::empty(format!("a{}", "日".repeat(Spell::MAX_NAME_LEN)));
+    spell.truncate_name();
+    assert_eq!(spell.name.chars().count(), Spell::MAX_NAME_LEN);
+    assert!(spell.name.ends_with('日'));
+
+    let mut short = Spell::empty("Blink");
+    short.truncate_name();
+    assert_eq!(short.name, "Blink");
+}
+
+#[test]
+fn builtin_params_are_recognised() {
+    assert_eq!(builtin_params().len(), 43);
+    assert_eq!(builtin_params()[0], "_target");
+    assert!(builtin_params().iter().all(|name| is_builtin_param(name)));
+    assert!(is_builtin_param("_vector1"));
+    assert!(!is_builtin_param("_custom"));
+    assert!(!is_builtin_param("target"));
+    assert!(!is_builtin_param(""));
+}
+
+#[test]
+fn piece_keys_are_counted_under_psi() {
+    let spell = Spell::builder()
+        .add_piece(0, 0, "psi:trick_add_motion")
+        .add_piece(1, 0, "trick_add_motion")
+        .add_piece(2, 0, "psi:selector_caster")
+        .add_piece(3, 0, "phi:trick_add_motion")
+        .build();
+    let counts = spell.piece_key_counts();
+    let counts: Vec<_> = counts.iter().map(|(k, &n)| (k.as_str(), n)).collect();
+    assert_eq!(
+        counts,
+        [
+            ("phi:trick_add_motion", 1),
+            ("psi:selector_caster", 1),
+            ("psi:trick_add_motion", 2),
+        ]
+    );
+    assert_eq!(spell.complexity(), 4);
+
+    let empty = Spell::empty("Empty");
+    assert!(empty.piece_key_counts().is_empty());
+    assert_eq!(empty.complexity(), 0);
+}
+
+#[test]
+fn keys_split_into_namespace_and_path() {
+    let spell = Spell::builder()
+        .add_piece(0, 0, "psi:foo")
+        .add_piece(1, 0, "phi:bar")
+        .add_piece(2, 0, "foo")
+        .add_piece(3, 0, "a:b:c")
+        .build();
+    let parts: Vec<_> = spell
+        .pieces
+        .iter()
+        .map(|p| (p.data.namespace(), p.data.path()))
+        .collect();
+    assert_eq!(
+        parts,
+        [("psi", "foo"), ("phi", "bar"), ("psi", "foo"), ("a", "b:c")]
+    );
+}
+
+#[test]
+fn bounds_cover_every_piece() {
+    let spell = Spell::builder()
+        .add_piece(2, 3, "psi:selector_caster")
+        .add_piece(5, 1, "psi:operator_entity_look")
+        .add_piece(4, 6, "psi:trick_blink")
+        .build();
+    assert_eq!(spell.bounds(), Some((2, 1, 5, 6)));
+
+    let single = Spell::builder().add_piece(7, 8, "psi:trick_blink").build();
+    assert_eq!(single.bounds(), Some((7, 8, 7, 8)));
+    assert_eq!(Spell::empty("Empty").bounds(), None);
+}
+
+#[test]
+fn key_index_lists_keys_in_first_seen_order() {
+    let spell = Spell::builder()
+        .add_piece(0, 0, "psi:selector_caster")
+        .add_piece(1, 0, "psi:trick_blink")
+        .add_piece(2, 0, "psi:selector_caster")
+        .add_piece(3, 0, "selector_caster")
+        .build();
+    let (keys, indices) = spell.key_index();
+    // Keys are compared as stored, so the unqualified one gets its own entry.
+    assert_eq!(
+        keys,
+        ["psi:selector_caster", "psi:trick_blink", "selector_caster"]
+    );
+    assert_eq!(indices, [0, 1, 0, 2]);
+
+    let (keys, indices) = Spell::empty("Empty").key_index();
+    assert!(keys.is_empty() && indices.is_empty());
+}
+
+#[test]
+fn empty_spells_round_trip() {
+    for name in ["", "Blank"] {
+        let spell = Spell::empty(name);
+        assert!(spell.is_empty());
+        assert_eq!(spell.name, name);
+        assert!(spell.mods.is_empty() && spell.meta.is_none());
+        assert_eq!(Spell::decode(&spell.bin()).unwrap(), spell);
+        let encoded = encode_spell(&spell).unwrap();
+        assert_eq!(decode_spell(&encoded).unwrap(), spell);
+    }
+
+    // Only pieces count, not the name or mods.
+    let mods_only = Spell::builder().add_mod("psi", "2.0.0").build();
+    assert!(mods_only.is_empty());
+    let blink = Spell::builder().add_piece(0, 0, "psi:trick_blink").build();
+    assert!(!blink.is_empty());
+}
+
+#[test]
+fn replace_key_counts_replacements() {
+    let mut spell = Spell::builder()
+        .add_piece(0, 0, "psi:trick_old")
+        .add_piece(1, 0, "trick_old")
+        .add_piece(2, 0, "psi:selector_caster")
+        .add_piece(3, 0, "phi:trick_old")
+        .build();
+    assert_eq!(spell.replace_key("trick_old", "psi:trick_new"), 2);
+    let keys: Vec<_> = spell.pieces.iter().map(|p| p.data.key.as_str()).collect();
+    assert_eq!(
+        keys,
+        [
+            "psi:trick_new",
+            "psi:trick_new",
+            "psi:selector_caster",
+            "phi:trick_old"
+        ]
+    );
+
+    let before = spell.clone();
+    assert_eq!(spell.replace_key("psi:trick_missing", "psi:trick_new"), 0);
+    assert_eq!(spell, before);
+}
+
+#[test]
+fn param_names_split_builtin_from_custom() {
+    let spell = Spell::builder()
+        .add_piece(0, 0, "psi:trick_blink")
+        .param("_target", Side::Left)
+        .param("_foo", Side::Top)
+        .add_piece(1, 0, "psi:trick_add_motion")
+        .param("_target", Side::Right)
+        .param("_direction", Side::Bottom)
+        .add_piece(2, 0, "psi:connector")
+        .build();
+    let (builtin, custom) = spell.param_names();
+    assert_eq!(Vec::from_iter(builtin), ["_direction", "_target"]);
+    assert_eq!(Vec::from_iter(custom), ["_foo"]);
+
+    let (builtin, custom) = Spell::empty("Empty").param_names();
+    assert!(builtin.is_empty() && custom.is_empty());
+}
+
+#[test]
+fn connection_count_sums_params() {
+    let spell = Spell::builder()
+        .add_piece(0, 0, "psi:operator_vector_sum")
+        .param("_vector1", Side::Left)
+        .param("_vector2", Side::Top)
+        .add_piece(1, 0, "psi:trick_add_motion")
+        .param("_target", Side::Left)
+        .param("_direction", Side::Top)
+        .param("_speed", Side::Off)
+        .add_piece(2, 0, "psi:constant_number")
+        .constant("4")
+        .build();
+    // Unconnected params are still counted.
+    assert_eq!(spell.connection_count(), 5);
+    assert_eq!(Spell::empty("Empty").connection_count(), 0);
+}
+
+#[test]
+fn sort_pieces_orders_by_row_then_column() {
+    let mut spell = Spell::builder()
+        .add_piece(2, 1, "psi:trick_blink")
+        .add_piece(0, 1, "psi:operator_entity_look")
+        .add_piece(1, 0, "psi:selector_caster")
+        .add_piece(0, 1, "psi:connector")
+        .build();
+    spell.sort_pieces();
+    let cells: Vec<_> = spell
+        .pieces
+        .iter()
+        .map(|p| (p.x, p.y, p.data.key.as_str()))
+        .collect();
+    // Pieces sharing a cell keep their relative order.
+    assert_eq!(
+        cells,
+        [
+            (1, 0, "psi:selector_caster"),
+            (0, 1, "psi:operator_entity_look"),
+            (0, 1, "psi:connector"),
+            (2, 1, "psi:trick_blink"),
+        ]
+    );
+
+    let sorted = spell.clone();
+    spell.sort_pieces();
+    assert_eq!(spell, sorted);
+}
+
+#[test]
+fn builtin_connections_skip_custom_and_unconnected_params() {
+    let spell = Spell::builder()
+        .add_piece(0, 0, "psi:trick_add_motion")
+        .param("_distance", Side::Bottom)
+        .param("_custom", Side::Left)
+        .param("_direction", Side::Off)
+        .param("_target", Side::Top)
+        .add_piece(1, 0, "psi:constant_number")
+        .constant("4")
+        .build();
+    // Reported in builtin order, not insertion order.
+    assert_eq!(
+        spell.pieces[0].data.builtin_connections(),
+        [
+            ("_target", Side::Top as u8),
+            ("_distance", Side::Bottom as u8)
+        ]
+    );
+    assert!(spell.pieces[1].data.builtin_connections().is_empty());
+}
+
+#[test]
+fn comments_are_listed_in_reading_order() {
+    let mut spell = Spell::builder()
+        .add_piece(2, 1, "psi:trick_blink")
+        .comment("blink")
+        .add_piece(0, 1, "psi:operator_entity_look")
+        .add_piece(1, 0, "psi:selector_caster")
+        .comment("me")
+        .add_piece(0, 2, "psi:connector")
+        .build();
+    spell.pieces[3].data.comment = Some(String::new());
+    // Empty comments are skipped like missing ones.
+    assert_eq!(spell.comments(), [(1, 0, "me"), (2, 1, "blink")]);
+    assert!(spell.without_comments().comments().is_empty());
+}
+
+#[test]
+fn ensure_psi_mod_adds_psi_once() {
+    let mut spell = Spell::builder().add_mod("phi", "1.0").build();
+    assert!(spell.requires_mod("phi"));
+    assert!(!spell.requires_mod("psi"));
+
+    spell.ensure_psi_mod("2.0.0");
+    let once = spell.clone();
+    spell.ensure_psi_mod("2.0.1");
+    assert_eq!(spell, once);
+    let mods: Vec<_> = spell.mods.iter().map(|m| (&*m.name, &*m.version)).collect();
+    assert_eq!(mods, [("psi", "2.0.0"), ("phi", "1.0")]);
+
+    // An existing entry is left where it is, with its version.
+    let mut spell = Spell::builder()
+        .add_mod("phi", "1.0")
+        .add_mod("psi", "1.1")
+        .build();
+    let before = spell.clone();
+    spell.ensure_psi_mod("2.0.0");
+    assert_eq!(spell, before);
+}
+
+#[test]
+fn find_pieces_filters_by_key() {
+    let mut spell = Spell::builder()
+        .add_piece(0, 0, "psi:selector_caster")
+        .add_piece(1, 0, "psi:trick_add_motion")
+        .add_piece(2, 0, "psi:trick_blink")
+        .build();
+    let is_trick = |key: &str| key.starts_with("psi:trick_");
+    let tricks: Vec<_> = spell
+        .find_pieces(is_trick)
+        .into_iter()
+        .map(|piece| (piece.x, piece.y))
+        .collect();
+    assert_eq!(tricks, [(1, 0), (2, 0)]);
+    assert!(spell.find_pieces(|key| key == "psi:missing").is_empty());
+
+    for piece in spell.find_pieces_mut(is_trick) {
+        piece.data.comment = Some("trick".to_owned());
+    }
+    let comments: Vec<_> = spell
+        .pieces
+        .iter()
+        .map(|p| p.data.comment.as_deref())
+        .collect();
+    assert_eq!(comments, [None, Some("trick"), Some("trick")]);
+}
+
+#[test]
+fn split_quadrants_puts_middle_pieces_top_left() {
+    let mut spell = Spell::builder()
+        .name("Blink")
+        .add_mod("psi", "2.0.0")
+        .add_piece(4, 4, "psi:selector_caster")
+        .add_piece(4, 0, "psi:connector")
+        .add_piece(0, 4, "psi:connector")
+        .add_piece(8, 4, "psi:connector")
+        .add_piece(4, 8, "psi:connector")
+        .add_piece(5, 5, "psi:trick_blink")
+        .build();
+    spell.meta = Some(SpellMeta {
+        author: "Vazkii".to_owned(),
+        created_unix: 1_700_000_000,
+    });
+    let parts = spell.split_quadrants();
+    let cells = |part: &Spell| part.pieces.iter().map(|p| (p.x, p.y)).collect::<Vec<_>>();
+    assert_eq!(cells(&parts[0]), [(4, 4), (4, 0), (0, 4)]);
+    assert_eq!(cells(&parts[1]), [(8, 4)]);
+    assert_eq!(cells(&parts[2]), [(4, 8)]);
+    assert_eq!(cells(&parts[3]), [(5, 5)]);
+
+    let names: Vec<_> = parts.iter().map(|part| part.name.as_str()).collect();
+    assert_eq!(
+        names,
+        [
+            "Blink (top left)",
+            "Blink (top right)",
+            "Blink (bottom left)",
+            "Blink (bottom right)"
+        ]
+    );
+    for part in &parts {
+        assert_eq!(part.mods, spell.mods);
+        assert_eq!(part.meta, spell.meta);
+    }
+}
+
+#[test]
+fn every_piece_kind_is_detected() {
+    let spell = Spell::builder()
+        .add_piece(0, 0, "psi:connector")
+        .add_piece(1, 0, "psi:constant_number")
+        .constant("4")
+        .add_piece(2, 0, "psi:trick_blink")
+        .param("_target", Side::Left)
+        .add_piece(3, 0, "psi:trick_blink")
+        .param("_target", Side::Left)
+        .constant("4")
+        .comment("params win")
+        .build();
+    let kind = |i: usize| spell.pieces[i].data.kind();
+    assert_eq!(kind(0), PieceKind::Empty);
+    assert_eq!(kind(1), PieceKind::Constant);
+    assert_eq!(kind(2), PieceKind::Connected);
+    assert_eq!(kind(3), PieceKind::Connected);
+}
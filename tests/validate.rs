@@ -0,0 +1,54 @@
+use psi_spell_encode::{GridSpec, Spell, SpellError};
+
+#[test]
+fn piece_outside_grid_fails_validation() {
+    let spell = Spell::builder()
+        .add_piece(0, 0, "psi:selector_caster")
+        .add_piece(20, 0, "psi:trick_blink")
+        .build();
+    assert_eq!(spell.validate(), Err(SpellError::OutOfGrid { x: 20, y: 0 }));
+}
+
+#[test]
+fn overlapping_pieces_are_reported() {
+    let spell = Spell::builder()
+        .add_piece(3, 3, "psi:selector_caster")
+        .add_piece(2, 3, "psi:operator_entity_look")
+        .add_piece(3, 3, "psi:trick_blink")
+        .build();
+    assert_eq!(spell.overlapping_pieces(), [(3, 3)]);
+    assert_eq!(
+        spell.validate(),
+        Err(SpellError::OverlappingPieces { x: 3, y: 3 })
+    );
+}
+
+#[test]
+fn delimiter_in_mod_version_is_rejected() {
+    let spell = Spell::builder()
+        .add_mod("psi", "1.0;beta")
+        .add_piece(0, 0, "psi:trick_blink")
+        .build();
+    let err = SpellError::ReservedByteInMod {
+        name: "psi".into(),
+        byte: b';',
+    };
+    assert_eq!(spell.try_bin(), Err(err.clone()));
+    assert_eq!(spell.validate(), Err(err));
+    // Unchecked, the `;` splits the version into a second mod.
+    let mods = Spell::decode(&spell.bin()).unwrap().mods;
+    let mods: Vec<_> = mods.iter().map(|m| (&*m.name, &*m.version)).collect();
+    assert_eq!(mods, [("psi", "1.0"), ("beta", "")]);
+}
+
+#[test]
+fn custom_grid_widens_the_limits() {
+    let spell = Spell::builder().add_piece(9, 0, "psi:trick_blink").build();
+    assert_eq!(spell.validate(), Err(SpellError::OutOfGrid { x: 9, y: 0 }));
+    let grid = GridSpec {
+        width: 16,
+        height: 16,
+    };
+    assert_eq!(spell.validate_in(grid), Ok(()));
+    assert_eq!(spell.validate_in(GridSpec::default()), spell.validate());
+}
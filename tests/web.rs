@@ -0,0 +1,40 @@
+//! Smoke test of the browser bindings. Run with
+//! `wasm-pack test --node -- --no-default-features --features wasm`.
+
+#![cfg(all(feature = "wasm", target_arch = "wasm32"))]
+
+use psi_spell_encode::{decode_spell, encode_spell, spell_to_snbt, wasm, Side, Spell};
+use wasm_bindgen::JsValue;
+use wasm_bindgen_test::wasm_bindgen_test;
+
+fn sample() -> Spell {
+    Spell::builder()
+        .name("Blink")
+        .add_mod("psi", "2.0.0")
+        .add_piece(0, 0, "psi:selector_caster")
+        .add_piece(1, 0, "psi:trick_blink")
+        .param("_target", Side::Left)
+        .build()
+}
+
+#[wasm_bindgen_test]
+fn share_string_round_trips_through_js() {
+    let encoded = encode_spell(&sample()).unwrap();
+    let js = wasm::decode_spell(&encoded).map_err(JsValue::from).unwrap();
+    assert!(js.is_object());
+    let encoded = wasm::encode_spell(js).map_err(JsValue::from).unwrap();
+    assert_eq!(decode_spell(&encoded).unwrap(), sample());
+}
+
+#[wasm_bindgen_test]
+fn snbt_round_trips_through_js() {
+    let snbt = spell_to_snbt(&sample()).unwrap();
+    let js = wasm::spell_from_snbt(&snbt).map_err(JsValue::from).unwrap();
+    let snbt_again = wasm::spell_to_snbt(js).map_err(JsValue::from).unwrap();
+    assert_eq!(snbt_again, snbt);
+}
+
+#[wasm_bindgen_test]
+fn invalid_share_string_is_an_error() {
+    assert!(wasm::decode_spell("not a spell").is_err());
+}